@@ -0,0 +1,48 @@
+use afire::{Content, Method, Request, Response, Server};
+use serde_json::Value;
+
+use crate::{respond, RemoteControl};
+
+pub(crate) type Any = Box<dyn Fn(&Request, &Value) + Send + Sync>;
+
+impl RemoteControl {
+    pub fn method(self, method: Method) -> Self {
+        Self { method, ..self }
+    }
+
+    /// (Request, ControlData)
+    /// Runs before every verified request to the afire route.
+    /// Use [`RemoteControl::on_action`] to also get the action, or when using another adapter.
+    pub fn any(self, exe: impl Fn(&Request, &Value) + Send + Sync + 'static) -> Self {
+        let mut afire_any = self.afire_any;
+        afire_any.push(Box::new(exe));
+        Self { afire_any, ..self }
+    }
+
+    /// Adds the control route to an afire server
+    pub fn attach<App>(self, server: &mut Server<App>)
+    where
+        App: 'static + Send + Sync,
+    {
+        if !self.enabled {
+            println!("[-] Chalk key not found. Disabling remote control.");
+            return;
+        }
+
+        let (method, path) = (self.method, self.path.to_owned());
+        server.route(method, path, move |req| {
+            let header = |name: &str| {
+                req.headers
+                    .iter()
+                    .find(|x| x.name.to_string().eq_ignore_ascii_case(name))
+                    .map(|x| x.value.as_str())
+            };
+            let any = |data: &Value| self.afire_any.iter().for_each(|x| x(req, data));
+            let (status, body) = respond(self.dispatch(header, &req.body, any));
+            Response::new()
+                .status(status)
+                .text(body)
+                .content(Content::JSON)
+        });
+    }
+}
@@ -0,0 +1,73 @@
+use std::future::Future;
+use std::pin::Pin;
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use crate::{action_not_found, respond, ControlError, RemoteControl, System};
+
+type SystemFuture = Pin<Box<dyn Future<Output = Result<Value, ControlError>> + Send>>;
+pub(crate) type AsyncSystem = Box<dyn Fn(Value) -> SystemFuture + Send + Sync>;
+
+impl RemoteControl {
+    /// Adds a system that is awaited by [`RemoteControl::handle_async`].
+    /// No runtime is needed, the future runs on whatever awaits `handle_async`.
+    pub fn system_async<F>(
+        self,
+        name: &str,
+        exe: impl Fn(Value) -> F + Send + Sync + 'static,
+    ) -> Self
+    where
+        F: Future<Output = Value> + Send + 'static,
+    {
+        self.add_system(
+            name,
+            System::Async(Box::new(move |data| {
+                let fut = exe(data);
+                Box::pin(async move { Ok(fut.await) })
+            })),
+        )
+    }
+
+    /// Adds an async system that takes and returns serde types, like [`RemoteControl::system_typed`]
+    pub fn system_async_typed<Req, Resp, F>(
+        self,
+        name: &str,
+        exe: impl Fn(Req) -> F + Send + Sync + 'static,
+    ) -> Self
+    where
+        Req: DeserializeOwned,
+        Resp: Serialize,
+        F: Future<Output = Result<Resp, ControlError>> + Send + 'static,
+    {
+        self.add_system(
+            name,
+            System::Async(Box::new(move |data| {
+                let fut = Req::deserialize(data)
+                    .map_err(|e| ControlError::bad_request("invalid_data", e))
+                    .map(&exe);
+                Box::pin(async move { Ok(serde_json::to_value(fut?.await?)?) })
+            })),
+        )
+    }
+
+    /// Same as [`RemoteControl::handle`], but awaits async systems.
+    /// Sync systems are still run in place, so they should be quick.
+    pub async fn handle_async<'a>(
+        &self,
+        header: impl Fn(&str) -> Option<&'a str>,
+        body: &[u8],
+    ) -> (u16, String) {
+        let res = match self.request(header, body, |_| ()) {
+            Ok((action, _)) if action == "list-actions" => Ok(self.list_actions()),
+            Ok((action, data)) => match self.systems.get(&action) {
+                Some(System::Async(i)) => i(data).await,
+                Some(System::Sync(i)) => i(&data),
+                None => Err(action_not_found()),
+            },
+            Err(e) => Err(e),
+        };
+
+        respond(res)
+    }
+}
@@ -0,0 +1,47 @@
+use std::fmt::Display;
+
+use serde_derive::{Deserialize, Serialize};
+use serde_json::json;
+
+/// Error a system responds with, sent as `{error, code}` JSON with `status` as the status code.
+/// Anything [`Display`] converts into an `internal_error` (500), so `?` works in handlers.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ControlError {
+    #[serde(skip, default = "default_status")]
+    pub status: u16,
+    /// Human readable message
+    pub error: String,
+    /// Machine readable kind (EX: invalid_data)
+    pub code: String,
+}
+
+impl ControlError {
+    pub fn new(status: u16, code: impl Into<String>, error: impl Display) -> Self {
+        Self {
+            status,
+            error: error.to_string(),
+            code: code.into(),
+        }
+    }
+
+    /// A 400 error, for requests the system can not handle
+    pub fn bad_request(code: impl Into<String>, error: impl Display) -> Self {
+        Self::new(400, code, error)
+    }
+
+    /// The status code and body to respond with
+    pub fn response(&self) -> (u16, String) {
+        let body = json!({ "error": self.error, "code": self.code });
+        (self.status, body.to_string())
+    }
+}
+
+impl<T: Display> From<T> for ControlError {
+    fn from(error: T) -> Self {
+        Self::new(500, "internal_error", error)
+    }
+}
+
+fn default_status() -> u16 {
+    500
+}
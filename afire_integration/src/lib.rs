@@ -1,59 +1,209 @@
-use std::{env, fmt::Display};
+//! Lets apps run by chalk expose `system`s the daemon can call with `chalk app control`.
+//!
+//! The core ([`RemoteControl::handle`]) only works on request bodies, so it can be used with any http stack.
+//! Adapters for `afire` (the `afire` feature) and a plain `std::net` listener (the `listener` feature) are included.
+//! With the `async` feature systems can also be async, and are run by [`RemoteControl::handle_async`] from the app's own runtime.
+//! Requests are signed by the daemon with an HMAC of the body, a timestamp and a nonce (see [`chalk_client::control`]).
+//!
+//! Apps can also send readiness, status text, metrics and events back to the daemon with a [`Reporter`],
+//! and write structured logs it can filter with a [`Logger`].
+
+use std::{
+    env, fs, process,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
 
-use afire::{Method, Request, Response, Server};
 use ahash::{HashMap, HashMapExt};
-use serde_json::Value;
+use chalk_client::control::{self, Direction, Nonces, VerifyError};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Value};
+
+#[cfg(feature = "afire")]
+mod afire_adapter;
+#[cfg(feature = "async")]
+mod async_systems;
+mod error;
+#[cfg(feature = "listener")]
+mod listener;
+mod logger;
+mod report;
+pub use error::ControlError;
+pub use logger::{LogLevel, Logger};
+pub use report::{AppEvent, AppReportRequest, Reporter};
+
+type SyncSystem = Box<dyn Fn(&Value) -> Result<Value, ControlError> + Send + Sync>;
+type Any = Box<dyn Fn(&str, &Value) + Send + Sync>;
 
-type System = Box<dyn Fn(&Value) -> Value + Send + Sync>;
-type Any = Box<dyn Fn(&Request, &Value) + Send + Sync>;
+enum System {
+    Sync(SyncSystem),
+    #[cfg(feature = "async")]
+    Async(async_systems::AsyncSystem),
+}
 
 pub struct RemoteControl {
     // == Route Config ==
-    method: Method,
+    #[cfg(feature = "afire")]
+    method: afire::Method,
     path: String,
 
     // == Systems ==
     systems: HashMap<String, System>,
     any: Vec<Any>,
+    #[cfg(feature = "afire")]
+    afire_any: Vec<afire_adapter::Any>,
 
     // == Other ==
     enabled: bool,
     verification: String,
+    nonces: Nonces,
+    start: Instant,
 }
 
 impl RemoteControl {
+    /// Reads the key chalk passed to the app, remote control is disabled if there is none
     pub fn new() -> Self {
-        let key = env::var("CHALK-KEY");
+        let key = read_key();
         Self {
-            method: Method::POST,
+            #[cfg(feature = "afire")]
+            method: afire::Method::POST,
             path: "/control".to_owned(),
 
             systems: HashMap::new(),
             any: Vec::new(),
+            #[cfg(feature = "afire")]
+            afire_any: Vec::new(),
 
-            enabled: key.is_ok(),
-            verification: key.unwrap_or_else(|_| "".to_owned()),
+            enabled: key.is_some(),
+            verification: key.unwrap_or_default(),
+            nonces: Nonces::default(),
+            start: Instant::now(),
         }
     }
 
     pub fn system(self, name: &str, exe: impl Fn(&Value) -> Value + Send + Sync + 'static) -> Self {
+        self.add_system(name, System::Sync(Box::new(move |data| Ok(exe(data)))))
+    }
+
+    /// Adds a system that takes and returns serde types.
+    /// Data that does not deserialize into `Req` is rejected with an `invalid_data` error.
+    ///
+    /// ```ignore
+    /// control.system_typed::<Resize, Size>("resize", |req| {
+    ///     if req.width == 0 {
+    ///         return Err(ControlError::bad_request("invalid_size", "Width can not be 0"));
+    ///     }
+    ///     Ok(pool.resize(req.width, req.height)?)
+    /// })
+    /// ```
+    pub fn system_typed<Req, Resp>(
+        self,
+        name: &str,
+        exe: impl Fn(Req) -> Result<Resp, ControlError> + Send + Sync + 'static,
+    ) -> Self
+    where
+        Req: DeserializeOwned,
+        Resp: Serialize,
+    {
+        self.add_system(
+            name,
+            System::Sync(Box::new(move |data| {
+                let req = Req::deserialize(data)
+                    .map_err(|e| ControlError::bad_request("invalid_data", e))?;
+                Ok(serde_json::to_value(exe(req)?)?)
+            })),
+        )
+    }
+
+    fn add_system(self, name: &str, exe: System) -> Self {
         let mut systems = self.systems;
-        systems.insert(name.to_owned(), Box::new(exe));
+        systems.insert(name.to_owned(), exe);
 
         Self { systems, ..self }
     }
 
-    /// (Request, ControlData)
-    pub fn any(self, exe: impl Fn(&Request, &Value) + Send + Sync + 'static) -> Self {
+    // == Built-in Systems ==
+
+    /// Adds a `health` system that runs `check`.
+    /// Responds with `{healthy: true}`, or an `unhealthy` error (503).
+    pub fn health(self, check: impl Fn() -> Result<(), String> + Send + Sync + 'static) -> Self {
+        self.system_typed("health", move |()| match check() {
+            Ok(()) => Ok(json!({ "healthy": true })),
+            Err(e) => Err(ControlError::new(503, "unhealthy", e)),
+        })
+    }
+
+    /// Adds a `version` system that responds with `{version}`.
+    /// Pass `env!("CARGO_PKG_VERSION")` for the app's crate version.
+    pub fn version(self, version: &str) -> Self {
+        let out = json!({ "version": version });
+        self.system("version", move |_| out.clone())
+    }
+
+    /// Adds a `reload-config` system that runs `reload`.
+    /// Responds with `{ok: true}`, or a `reload_failed` error (500).
+    pub fn reload_config(
+        self,
+        reload: impl Fn() -> Result<(), String> + Send + Sync + 'static,
+    ) -> Self {
+        self.system_typed("reload-config", move |()| match reload() {
+            Ok(()) => Ok(json!({ "ok": true })),
+            Err(e) => Err(ControlError::new(500, "reload_failed", e)),
+        })
+    }
+
+    /// Adds a `graceful-shutdown` system that sets `flag`.
+    /// The app should poll it and exit once its done with any work in progress.
+    pub fn graceful_shutdown(self, flag: Arc<AtomicBool>) -> Self {
+        self.system("graceful-shutdown", move |_| {
+            flag.store(true, Ordering::Release);
+            json!({ "ok": true })
+        })
+    }
+
+    /// Adds a `log-level` system.
+    /// `level` is called with the new level if the data is a string, or None to just read it, and returns the current level.
+    /// Responds with `{level}`, or an `invalid_level` error (400).
+    pub fn log_level(
+        self,
+        level: impl Fn(Option<&str>) -> Result<String, String> + Send + Sync + 'static,
+    ) -> Self {
+        self.system_typed("log-level", move |new: Option<String>| {
+            match level(new.as_deref()) {
+                Ok(i) => Ok(json!({ "level": i })),
+                Err(e) => Err(ControlError::bad_request("invalid_level", e)),
+            }
+        })
+    }
+
+    /// Adds a `stats` system that responds with the process's `pid` and `uptime` (seconds).
+    /// Any fields in the object `extra` returns are added.
+    pub fn stats(self, extra: impl Fn() -> Value + Send + Sync + 'static) -> Self {
+        let start = self.start;
+        self.system("stats", move |_| {
+            let mut out = json!({
+                "pid": process::id(),
+                "uptime": start.elapsed().as_secs(),
+            });
+            if let (Some(out), Value::Object(extra)) = (out.as_object_mut(), extra()) {
+                out.extend(extra);
+            }
+            out
+        })
+    }
+
+    /// Runs before every verified request, with its action and data.
+    /// Unlike the afire only [`RemoteControl::any`], this works with every adapter.
+    pub fn on_action(self, exe: impl Fn(&str, &Value) + Send + Sync + 'static) -> Self {
         let mut any = self.any;
         any.push(Box::new(exe));
         Self { any, ..self }
     }
 
-    pub fn method(self, method: Method) -> Self {
-        Self { method, ..self }
-    }
-
+    /// Path the adapters serve the control route on
     pub fn path<T: AsRef<str>>(self, path: T) -> Self {
         Self {
             path: path.as_ref().to_owned(),
@@ -76,48 +226,163 @@ impl RemoteControl {
         }
     }
 
-    pub fn attach<App>(self, server: &mut Server<App>)
-    where
-        App: 'static + Send + Sync,
-    {
+    /// If the key file from chalk was found (or it was enabled for debugging).
+    /// Adapters do nothing when this is false.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Path to mount [`RemoteControl::handle`] on when using another http stack
+    pub fn route_path(&self) -> &str {
+        &self.path
+    }
+
+    /// Verifies and runs a control request, returning the status code and JSON body of the response.
+    /// `header` looks up a request header by its name, ignoring case.
+    /// Sync systems block, so from async code run it on a blocking task or use `handle_async`.
+    /// Async systems respond with an `async_system` error (500) here.
+    pub fn handle<'a>(
+        &self,
+        header: impl Fn(&str) -> Option<&'a str>,
+        body: &[u8],
+    ) -> (u16, String) {
+        respond(self.dispatch(header, body, |_| ()))
+    }
+
+    /// Runs the system for a request, `any` is run with its data once it is verified
+    fn dispatch<'a>(
+        &self,
+        header: impl Fn(&str) -> Option<&'a str>,
+        body: &[u8],
+        any: impl Fn(&Value),
+    ) -> Result<Value, ControlError> {
+        let (action, data) = self.request(header, body, any)?;
+        if action == "list-actions" {
+            return Ok(self.list_actions());
+        }
+
+        match self.systems.get(&action) {
+            Some(System::Sync(i)) => i(&data),
+            #[cfg(feature = "async")]
+            Some(System::Async(_)) => Err(ControlError::new(
+                500,
+                "async_system",
+                format!("`{action}` is async, it needs `handle_async`"),
+            )),
+            None => Err(action_not_found()),
+        }
+    }
+
+    /// Verifies and parses a request and runs the `on_action` handlers then `any`, returning its action and data
+    fn request<'a>(
+        &self,
+        header: impl Fn(&str) -> Option<&'a str>,
+        body: &[u8],
+        any: impl Fn(&Value),
+    ) -> Result<(String, Value), ControlError> {
         if !self.enabled {
-            println!("[-] Chalk key not found. Disabling remote control.");
-            return;
+            return Err(ControlError::new(
+                503,
+                "disabled",
+                "Remote control is disabled",
+            ));
         }
 
-        server.route(self.method, self.path, move |req| {
-            let data = match serde_json::from_str::<Value>(&String::from_utf8_lossy(&req.body)) {
-                Ok(i) => i,
-                Err(e) => return err(format!("Invalid JSON: {e}")),
-            };
+        self.verify(header, body)?;
+        let data = serde_json::from_slice::<Value>(body)
+            .map_err(|e| ControlError::bad_request("invalid_json", format!("Invalid JSON: {e}")))?;
 
-            let verification = match data.get("verification").and_then(|i| i.as_str()) {
-                Some(i) => i,
-                None => return err("Missing Verification Token"),
-            };
-            if verification != self.verification {
-                return err("Invalid Verification Token");
+        let action = match data.get("action").and_then(|i| i.as_str()) {
+            Some(i) => i,
+            None => {
+                return Err(ControlError::bad_request(
+                    "missing_action",
+                    "Missing Action",
+                ))
             }
+        };
+        if !self.systems.contains_key(action) && action != "list-actions" {
+            return Err(action_not_found());
+        }
 
-            let action = match data.get("action").and_then(|i| i.as_str()) {
-                Some(i) => i,
-                None => return err("Missing Action"),
-            };
-            let executer = match self.systems.get(action) {
-                Some(i) => i,
-                None => return err("Action not found"),
+        let data = data.get("data").cloned().unwrap_or(Value::Null);
+        self.any.iter().for_each(|x| x(action, &data));
+        any(&data);
+        Ok((action.to_owned(), data))
+    }
+
+    /// Response to `list-actions`, so the daemon can discover what the app supports
+    fn list_actions(&self) -> Value {
+        json!({ "actions": self.actions() })
+    }
+
+    /// Checks the request's HMAC signature and that it is fresh and not a replay
+    fn verify<'a>(
+        &self,
+        header: impl Fn(&str) -> Option<&'a str>,
+        body: &[u8],
+    ) -> Result<(), ControlError> {
+        let unauthorized = |code: &str, error: &str| ControlError::new(401, code, error);
+        let (timestamp, nonce, signature) = match (
+            header(control::TIMESTAMP_HEADER),
+            header(control::NONCE_HEADER),
+            header(control::SIGNATURE_HEADER),
+        ) {
+            (Some(i), Some(j), Some(k)) => (i, j, k),
+            _ => return Err(unauthorized("missing_signature", "Missing Signature")),
+        };
+
+        let time = control::verify(
+            &self.verification,
+            Direction::Control,
+            timestamp,
+            nonce,
+            signature,
+            body,
+        )
+        .map_err(|e| {
+            let code = match e {
+                VerifyError::InvalidTimestamp => "invalid_timestamp",
+                VerifyError::Expired => "expired",
+                VerifyError::InvalidSignature => "invalid_signature",
             };
+            unauthorized(code, &e.to_string())
+        })?;
+
+        if !self.nonces.insert(nonce, time) {
+            return Err(unauthorized("replayed", "Request Replayed"));
+        }
+
+        Ok(())
+    }
+
+    /// Names of all the systems, sorted
+    fn actions(&self) -> Vec<&str> {
+        let mut out = self.systems.keys().map(|x| x.as_str()).collect::<Vec<_>>();
+        out.push("list-actions");
+        out.sort_unstable();
+        out
+    }
+}
 
-            let data = data.get("data").unwrap_or(&Value::Null);
-            self.any.iter().for_each(|x| x(req, data));
-            let out = executer(data);
-            Response::new().text(out)
-        });
+/// The status code and JSON body to respond to a control request with
+pub(crate) fn respond(res: Result<Value, ControlError>) -> (u16, String) {
+    match res {
+        Ok(i) => (200, i.to_string()),
+        Err(e) => e.response(),
     }
 }
 
-fn err(error: impl Display) -> Response {
-    Response::new().status(400).text(error)
+pub(crate) fn action_not_found() -> ControlError {
+    ControlError::new(404, "action_not_found", "Action not found")
+}
+
+/// Reads the key from the file chalk passes the path of in `CHALK_KEY_FILE`.
+/// The file is rewritten with a new key every time the app is started.
+pub(crate) fn read_key() -> Option<String> {
+    let path = env::var_os(control::KEY_FILE_ENV)?;
+    let key = fs::read_to_string(path).ok()?;
+    Some(key.trim().to_owned()).filter(|x| !x.is_empty())
 }
 
 impl Default for RemoteControl {
@@ -125,3 +390,119 @@ impl Default for RemoteControl {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use chalk_client::control::SignedHeaders;
+
+    use super::*;
+
+    const KEY: &str = "chalk-test-key";
+    const BODY: &str = r#"{"action":"echo","data":1}"#;
+
+    fn remote() -> RemoteControl {
+        RemoteControl {
+            enabled: true,
+            verification: KEY.to_owned(),
+            ..RemoteControl::new()
+        }
+        .system("echo", |x| x.clone())
+    }
+
+    fn send(remote: &RemoteControl, signed: &SignedHeaders, timestamp: &str) -> (u16, String) {
+        let headers = [
+            (control::TIMESTAMP_HEADER, timestamp),
+            (control::NONCE_HEADER, signed.nonce.as_str()),
+            (control::SIGNATURE_HEADER, signed.signature.as_str()),
+        ];
+        remote.handle(
+            |name| headers.iter().find(|x| x.0 == name).map(|x| x.1),
+            BODY.as_bytes(),
+        )
+    }
+
+    fn call(remote: &RemoteControl, body: &str) -> (u16, String) {
+        let signed = control::sign(KEY, Direction::Control, body.as_bytes());
+        let headers = [
+            (control::TIMESTAMP_HEADER, signed.timestamp.as_str()),
+            (control::NONCE_HEADER, signed.nonce.as_str()),
+            (control::SIGNATURE_HEADER, signed.signature.as_str()),
+        ];
+        remote.handle(
+            |name| headers.iter().find(|x| x.0 == name).map(|x| x.1),
+            body.as_bytes(),
+        )
+    }
+
+    fn code(res: &(u16, String)) -> String {
+        let body = serde_json::from_str::<Value>(&res.1).unwrap();
+        body["code"].as_str().unwrap_or_default().to_owned()
+    }
+
+    #[test]
+    fn accepts_control_requests() {
+        let signed = control::sign(KEY, Direction::Control, BODY.as_bytes());
+        assert_eq!(
+            send(&remote(), &signed, &signed.timestamp),
+            (200, "1".to_owned())
+        );
+    }
+
+    #[test]
+    fn rejects_report_signatures() {
+        let signed = control::sign(KEY, Direction::Report, BODY.as_bytes());
+        let res = send(&remote(), &signed, &signed.timestamp);
+        assert_eq!((res.0, code(&res)), (401, "invalid_signature".to_owned()));
+    }
+
+    #[test]
+    fn rejects_stale_requests() {
+        let signed = control::sign(KEY, Direction::Control, BODY.as_bytes());
+        let stale = (control::now() - control::MAX_AGE - 1).to_string();
+        let res = send(&remote(), &signed, &stale);
+        assert_eq!((res.0, code(&res)), (401, "expired".to_owned()));
+    }
+
+    #[test]
+    fn rejects_replayed_requests() {
+        let remote = remote();
+        let signed = control::sign(KEY, Direction::Control, BODY.as_bytes());
+        assert_eq!(send(&remote, &signed, &signed.timestamp).0, 200);
+
+        let res = send(&remote, &signed, &signed.timestamp);
+        assert_eq!((res.0, code(&res)), (401, "replayed".to_owned()));
+    }
+
+    #[test]
+    fn lists_actions() {
+        let res = call(&remote(), r#"{"action":"list-actions"}"#);
+        assert_eq!(
+            res,
+            (200, r#"{"actions":["echo","list-actions"]}"#.to_owned())
+        );
+    }
+
+    #[test]
+    fn rejects_missing_actions() {
+        let res = call(&remote(), r#"{"data":1}"#);
+        assert_eq!((res.0, code(&res)), (400, "missing_action".to_owned()));
+
+        let res = call(&remote(), r#"{"action":"other"}"#);
+        assert_eq!((res.0, code(&res)), (404, "action_not_found".to_owned()));
+    }
+
+    #[test]
+    fn runs_on_action_handlers() {
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let remote = {
+            let seen = seen.clone();
+            remote().on_action(move |action, data| {
+                seen.lock().unwrap().push((action.to_owned(), data.clone()))
+            })
+        };
+
+        call(&remote, BODY);
+        call(&remote, r#"{"action":"other"}"#);
+        assert_eq!(*seen.lock().unwrap(), vec![("echo".to_owned(), json!(1))]);
+    }
+}
@@ -0,0 +1,101 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use crate::{ControlError, RemoteControl};
+
+/// Largest request body that will be read
+const MAX_BODY: usize = 1024 * 1024;
+
+impl RemoteControl {
+    /// Serves the control route on its own port, for apps not using afire.
+    /// Only `POST` requests to the path are handled, each connection on its own thread.
+    pub fn listen(self, addr: impl ToSocketAddrs) -> io::Result<()> {
+        if !self.enabled {
+            println!("[-] Chalk key not found. Disabling remote control.");
+            return Ok(());
+        }
+
+        let listener = TcpListener::bind(addr)?;
+        let this = Arc::new(self);
+        thread::Builder::new()
+            .name("RemoteControl".to_owned())
+            .spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    let this = this.clone();
+                    thread::spawn(move || {
+                        let _ = this.serve(stream);
+                    });
+                }
+            })?;
+
+        Ok(())
+    }
+
+    fn serve(&self, mut stream: TcpStream) -> io::Result<()> {
+        stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+
+        // POST /control HTTP/1.1
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let mut parts = line.split_whitespace();
+        let (method, path) = (
+            parts.next().unwrap_or_default(),
+            parts.next().unwrap_or_default(),
+        );
+
+        let mut headers = Vec::new();
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+                break;
+            }
+
+            if let Some((key, value)) = line.split_once(':') {
+                headers.push((key.trim().to_owned(), value.trim().to_owned()));
+            }
+        }
+        let header = |name: &str| {
+            headers
+                .iter()
+                .find(|x| x.0.eq_ignore_ascii_case(name))
+                .map(|x| x.1.as_str())
+        };
+        let length = header("Content-Length")
+            .and_then(|x| x.parse().ok())
+            .unwrap_or(0);
+
+        let (status, body) = if method != "POST" || path != self.path {
+            ControlError::new(404, "not_found", "Not Found").response()
+        } else if length > MAX_BODY {
+            ControlError::new(413, "too_large", "Body too large").response()
+        } else {
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body)?;
+            self.handle(header, &body)
+        };
+
+        write!(
+            stream,
+            "HTTP/1.1 {status} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            reason(status),
+            body.len()
+        )
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        413 => "Payload Too Large",
+        500 => "Internal Server Error",
+        503 => "Service Unavailable",
+        _ => "Unknown",
+    }
+}
@@ -0,0 +1,90 @@
+use std::fmt::Display;
+use std::io::{self, Write};
+use std::sync::{
+    atomic::{AtomicU8, Ordering},
+    Arc,
+};
+
+pub use chalk_client::models::LogLevel;
+use serde_json::{json, Value};
+
+const LEVELS: [LogLevel; 5] = [
+    LogLevel::Trace,
+    LogLevel::Debug,
+    LogLevel::Info,
+    LogLevel::Warn,
+    LogLevel::Error,
+];
+
+/// Writes logs to stdout as JSON lines, which the daemon keeps as structured records for `chalk app logs`.
+/// Clones share their minimum level, so it can be changed from a `log_level` system.
+///
+/// ```ignore
+/// let log = Logger::new("my_app::db");
+/// log.info("Connected");
+/// log.log(LogLevel::Warn, "Slow query", json!({ "ms": 1200 }));
+/// ```
+#[derive(Clone)]
+pub struct Logger {
+    target: String,
+    level: Arc<AtomicU8>,
+}
+
+impl Logger {
+    /// Makes a logger for `target`, logging `info` and above
+    pub fn new(target: impl Into<String>) -> Self {
+        Self {
+            target: target.into(),
+            level: Arc::new(AtomicU8::new(LogLevel::Info as u8)),
+        }
+    }
+
+    /// Gets the minimum level that is written
+    pub fn level(&self) -> LogLevel {
+        LEVELS[self.level.load(Ordering::Relaxed) as usize]
+    }
+
+    /// Sets the minimum level that is written
+    pub fn set_level(&self, level: LogLevel) {
+        self.level.store(level as u8, Ordering::Relaxed);
+    }
+
+    /// Writes a record, the keys of `fields` (if it is an object) can be filtered on by the daemon
+    pub fn log(&self, level: LogLevel, message: impl Display, fields: Value) {
+        if level < self.level() {
+            return;
+        }
+
+        let mut record = json!({
+            "level": level,
+            "target": self.target,
+            "message": message.to_string(),
+        });
+        if let Value::Object(fields) = fields {
+            record["fields"] = Value::Object(fields);
+        }
+
+        // One write per line so records from different threads don't mix
+        let _ = writeln!(io::stdout().lock(), "{record}");
+    }
+
+    pub fn trace(&self, message: impl Display) {
+        self.log(LogLevel::Trace, message, Value::Null);
+    }
+
+    pub fn debug(&self, message: impl Display) {
+        self.log(LogLevel::Debug, message, Value::Null);
+    }
+
+    pub fn info(&self, message: impl Display) {
+        self.log(LogLevel::Info, message, Value::Null);
+    }
+
+    pub fn warn(&self, message: impl Display) {
+        self.log(LogLevel::Warn, message, Value::Null);
+    }
+
+    pub fn error(&self, message: impl Display) {
+        self.log(LogLevel::Error, message, Value::Null);
+    }
+}
@@ -0,0 +1,117 @@
+use std::env;
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+use chalk_client::control::{self, Direction};
+pub use chalk_client::models::{AppEvent, AppReportRequest};
+
+use crate::read_key;
+
+/// Sends status reports from the app to the daemon.
+///
+/// ```ignore
+/// let reporter = Reporter::new();
+/// if let Some(i) = &reporter {
+///     i.ready()?;
+/// }
+/// ```
+pub struct Reporter {
+    url: String,
+    key: String,
+}
+
+impl Reporter {
+    /// Reads the daemon's url and the app's key, None if the app was not started by chalk
+    pub fn new() -> Option<Self> {
+        Some(Self {
+            url: env::var(control::REPORT_URL_ENV).ok()?,
+            key: read_key()?,
+        })
+    }
+
+    /// Tells the daemon the app is ready to serve
+    pub fn ready(&self) -> io::Result<()> {
+        self.send(&AppReportRequest {
+            ready: Some(true),
+            ..Default::default()
+        })
+    }
+
+    /// Sets the status text shown in `chalk app info`
+    pub fn status(&self, status: impl Into<String>) -> io::Result<()> {
+        self.send(&AppReportRequest {
+            status: Some(status.into()),
+            ..Default::default()
+        })
+    }
+
+    /// Updates some metrics, metrics not included keep their last value
+    pub fn metrics<T: Into<String>>(
+        &self,
+        metrics: impl IntoIterator<Item = (T, f64)>,
+    ) -> io::Result<()> {
+        self.send(&AppReportRequest {
+            metrics: metrics.into_iter().map(|(k, v)| (k.into(), v)).collect(),
+            ..Default::default()
+        })
+    }
+
+    /// Asks the daemon to restart the app
+    pub fn restart(&self) -> io::Result<()> {
+        self.event(AppEvent::Restart)
+    }
+
+    /// Tells the daemon the app is reloading its config
+    pub fn reloading(&self) -> io::Result<()> {
+        self.event(AppEvent::Reloading)
+    }
+
+    fn event(&self, event: AppEvent) -> io::Result<()> {
+        self.send(&AppReportRequest {
+            event: Some(event),
+            ..Default::default()
+        })
+    }
+
+    /// Sends a signed report, erroring if the daemon rejects it
+    pub fn send(&self, report: &AppReportRequest) -> io::Result<()> {
+        let body = serde_json::to_string(report)?;
+        let signed = control::sign(&self.key, Direction::Report, body.as_bytes());
+
+        let invalid = |e: &str| io::Error::new(io::ErrorKind::InvalidInput, e);
+        let rest = self
+            .url
+            .strip_prefix("http://")
+            .ok_or_else(|| invalid("Only http urls are supported"))?;
+        let (host, path) = rest
+            .split_once('/')
+            .ok_or_else(|| invalid("Invalid report url"))?;
+
+        let mut stream = TcpStream::connect(host)?;
+        stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+        write!(
+            stream,
+            "POST /{path} HTTP/1.0\r\nHost: {host}\r\nConnection: close\r\nContent-Length: {}\r\n{}: {}\r\n{}: {}\r\n{}: {}\r\n\r\n{body}",
+            body.len(),
+            control::TIMESTAMP_HEADER,
+            signed.timestamp,
+            control::NONCE_HEADER,
+            signed.nonce,
+            control::SIGNATURE_HEADER,
+            signed.signature,
+        )?;
+
+        let mut res = String::new();
+        stream.read_to_string(&mut res)?;
+
+        // HTTP/1.1 200 OK
+        match res.split_whitespace().nth(1) {
+            Some("200") => Ok(()),
+            _ => {
+                let body = res.split_once("\r\n\r\n").map(|x| x.1).unwrap_or_default();
+                Err(io::Error::other(format!("Report rejected: {body}")))
+            }
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use clap::{value_parser, Arg, Command};
+use clap::{value_parser, Arg, ArgAction, Command};
 
 use crate::{commands::Commands, VERSION};
 
@@ -56,8 +56,42 @@ pub fn from_env() -> Commands {
                         .short('l')
                         .long("lines")
                         .help("Defines the number of lines to load"),
+                    Arg::new("level")
+                        .num_args(1)
+                        .short('L')
+                        .long("level")
+                        .help("Only shows logs at or above this level"),
+                    Arg::new("source")
+                        .num_args(1)
+                        .short('s')
+                        .long("source")
+                        .help("Only shows logs from `daemon`, `web` or an app"),
+                ]),
+            Command::new("rescan")
+                .about("Loads any new apps in the daemons app folder")
+                .args(&base),
+            Command::new("sign")
+                .about("Signs a binary or bundle with an ed25519 key for apps with trusted keys")
+                .args([
+                    Arg::new("file")
+                        .required_unless_present("generate")
+                        .help("Path to the binary or bundle to sign"),
+                    Arg::new("key")
+                        .num_args(1)
+                        .required(true)
+                        .short('k')
+                        .long("key")
+                        .help("Path to the hex encoded secret key"),
+                    Arg::new("out")
+                        .num_args(1)
+                        .short('o')
+                        .long("out")
+                        .help("Where to write the signature (defaults to [file].sig)"),
+                    Arg::new("generate")
+                        .long("generate")
+                        .action(ArgAction::SetTrue)
+                        .help("Makes a new key at the key path instead of signing"),
                 ]),
-            // Command::new("load").about("Loads new projects").arg(host),
             Command::new("app")
                 .about("Commands that interact with a daemons app")
                 .subcommand_required(true)
@@ -75,6 +109,124 @@ pub fn from_env() -> Commands {
                         .args(&base)
                         .arg(Arg::new("app").required(true))
                         .arg(Arg::new("signal").num_args(1)),
+                    Command::new("create")
+                        .about("Creates an app from a config file")
+                        .args(&base)
+                        .arg(
+                            Arg::new("config")
+                                .required(true)
+                                .help("Path to the apps config.toml"),
+                        ),
+                    Command::new("delete")
+                        .about("Stops and deletes an app")
+                        .args(&base)
+                        .arg(Arg::new("app").required(true))
+                        .arg(
+                            Arg::new("archive")
+                                .short('a')
+                                .long("archive")
+                                .action(ArgAction::SetTrue)
+                                .help("Moves the app folder to the daemons archive instead"),
+                        ),
+                    Command::new("deploy")
+                        .about("Uploads a binary or bundle as a new release of an app")
+                        .args(&base)
+                        .arg(Arg::new("app").required(true))
+                        .arg(
+                            Arg::new("file").required(true).help(
+                                "Path to the binary or bundle (.tar.gz, .tgz, .zip) to upload",
+                            ),
+                        )
+                        .arg(
+                            Arg::new("signature")
+                                .num_args(1)
+                                .short('s')
+                                .long("signature")
+                                .help("Path to a signature from `chalk sign` (defaults to [file].sig if it exists)"),
+                        ),
+                    Command::new("releases")
+                        .about("Lists the kept releases of an app")
+                        .args(&base)
+                        .arg(Arg::new("app").required(true)),
+                    Command::new("rollback")
+                        .about("Switches an app back to an older release")
+                        .args(&base)
+                        .arg(Arg::new("app").required(true))
+                        .arg(
+                            Arg::new("release")
+                                .value_parser(value_parser!(u32))
+                                .help("The release to switch to (defaults to the previous one)"),
+                        ),
+                    Command::new("control")
+                        .about("Runs an action on the app's remote control")
+                        .args(&base)
+                        .arg(Arg::new("app").required(true))
+                        .arg(
+                            Arg::new("action")
+                                .required(true)
+                                .help("Name of the system to run"),
+                        )
+                        .arg(Arg::new("data").help("JSON data to send with the action")),
+                    Command::new("logs")
+                        .about("Shows the structured (JSON line) logs of an app")
+                        .args(&base)
+                        .args([
+                            Arg::new("app").required(true),
+                            Arg::new("page")
+                                .num_args(1)
+                                .value_parser(value_parser!(usize))
+                                .short('p')
+                                .long("page")
+                                .help("The page to show, counting back from the newest"),
+                            Arg::new("lines")
+                                .num_args(1)
+                                .value_parser(value_parser!(usize))
+                                .short('l')
+                                .long("lines")
+                                .help("Defines the number of records per page (defaults to 50)"),
+                            Arg::new("level")
+                                .num_args(1)
+                                .short('L')
+                                .long("level")
+                                .help("Only shows records at or above this level"),
+                            Arg::new("target")
+                                .num_args(1)
+                                .long("target")
+                                .help("Only shows records with a target starting with this"),
+                            Arg::new("field")
+                                .num_args(1)
+                                .action(ArgAction::Append)
+                                .short('f')
+                                .long("field")
+                                .help("Only shows records with a field (key=value), can be used more than once"),
+                        ]),
+                ]),
+            Command::new("config")
+                .about("Commands that work with daemon and app configs")
+                .subcommand_required(true)
+                .subcommands([
+                    Command::new("check")
+                        .about("Checks a config for problems without loading it")
+                        .long_about(
+                            "Checks a config for problems without loading it. \
+                         If no path is given the daemon checks all the configs it has on disk.",
+                        )
+                        .args(&base)
+                        .args([
+                            Arg::new("path").help("Path to the config to check"),
+                            Arg::new("app").num_args(1).short('a').long("app").help(
+                                "The app a project config is for (checked as a new app if not set)",
+                            ),
+                            Arg::new("kind")
+                                .num_args(1)
+                                .short('k')
+                                .long("kind")
+                                .value_parser(["daemon", "project"])
+                                .help("The type of config (detected if not set)"),
+                        ]),
+                    Command::new("reload")
+                        .about("Makes the daemon reload its config without restarting apps")
+                        .args(&base),
                 ]),
         ])
         .get_matches();
@@ -0,0 +1,39 @@
+use clap::ArgMatches;
+use colored::Colorize;
+use serde_json::Value;
+
+use crate::misc;
+
+pub fn run(args: ArgMatches) {
+    let name = args.get_one::<String>("app").unwrap();
+    let action = args.get_one::<String>("action").unwrap();
+    let data = match args.get_one::<String>("data") {
+        Some(i) => match serde_json::from_str::<Value>(i) {
+            Ok(i) => i,
+            Err(e) => {
+                println!("{} ({})", "[-] Invalid JSON data".red(), e);
+                return;
+            }
+        },
+        None => Value::Null,
+    };
+
+    // Get host
+    let client = match misc::host_stuff(&args) {
+        Some(i) => i,
+        None => return,
+    };
+
+    let res = misc::ok_or_exit(client.app_control(name, action, data));
+    let status = match res.status {
+        200..=299 => res.status.to_string().green(),
+        _ => res.status.to_string().red(),
+    };
+    println!("{} {}", "Status:".blue(), status);
+
+    // Apps not using `RemoteControl` could respond with anything
+    match serde_json::from_str::<Value>(&res.body) {
+        Ok(i) => println!("{}", serde_json::to_string_pretty(&i).unwrap()),
+        Err(_) => println!("{}", res.body),
+    }
+}
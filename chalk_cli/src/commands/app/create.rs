@@ -0,0 +1,26 @@
+use std::fs;
+
+use clap::ArgMatches;
+use colored::Colorize;
+
+use crate::misc;
+
+pub fn run(args: ArgMatches) {
+    let path = args.get_one::<String>("config").unwrap();
+    let config = match fs::read_to_string(path) {
+        Ok(i) => i,
+        Err(e) => {
+            println!("{} ({})", "[-] Error reading config".red(), e);
+            return;
+        }
+    };
+
+    // Get host
+    let client = match misc::host_stuff(&args) {
+        Some(i) => i,
+        None => return,
+    };
+
+    let res = misc::ok_or_exit(client.app_create(&config));
+    println!("{} {}", "Created".green(), res.name.magenta());
+}
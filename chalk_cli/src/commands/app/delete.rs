@@ -0,0 +1,21 @@
+use clap::ArgMatches;
+use colored::Colorize;
+
+use crate::misc;
+
+pub fn run(args: ArgMatches) {
+    let name = args.get_one::<String>("app").unwrap();
+    let archive = args.get_flag("archive");
+
+    // Get host
+    let client = match misc::host_stuff(&args) {
+        Some(i) => i,
+        None => return,
+    };
+
+    let res = misc::ok_or_exit(client.app_delete(name, archive));
+    match res.archive {
+        Some(i) => println!("{} `{}`", "Archived to".green(), i),
+        None => println!("{}", "Ok".green()),
+    }
+}
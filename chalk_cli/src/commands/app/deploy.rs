@@ -0,0 +1,73 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use chalk_client::models::BundleKind;
+use clap::ArgMatches;
+use colored::Colorize;
+
+use crate::misc;
+
+const BAR_WIDTH: u64 = 30;
+
+pub fn run(args: ArgMatches) {
+    let name = args.get_one::<String>("app").unwrap();
+    let file = args.get_one::<String>("file").unwrap();
+
+    // Get host
+    let client = match misc::host_stuff(&args) {
+        Some(i) => i,
+        None => return,
+    };
+
+    // Archives are unpacked as a whole folder
+    let bundle = BundleKind::from_name(file);
+    if let Some(i) = bundle {
+        println!("Deploying {} as a {i} bundle", file.bold());
+    }
+
+    // Signatures from `chalk sign` are picked up automatically
+    let signature_path = args
+        .get_one::<String>("signature")
+        .cloned()
+        .unwrap_or_else(|| format!("{file}.sig"));
+    let signature = match fs::read_to_string(&signature_path) {
+        Ok(i) => Some(i),
+        Err(_) if args.contains_id("signature") => {
+            println!("{}", format!("[-] Error reading `{signature_path}`").red());
+            return;
+        }
+        Err(_) => None,
+    };
+
+    // [##########--------------------] 33% 1.2 MiB / 3.6 MiB
+    let mut last = None;
+    let res = client.app_upload(
+        name,
+        Path::new(file),
+        bundle,
+        signature.as_deref(),
+        |sent, total| {
+            let percent = (sent * 100).checked_div(total).unwrap_or(100);
+            if last == Some(percent) {
+                return;
+            }
+            last = Some(percent);
+
+            let filled = percent * BAR_WIDTH / 100;
+            print!(
+                "\r[{}{}] {:>3}% {} / {}",
+                "#".repeat(filled as usize).green(),
+                "-".repeat((BAR_WIDTH - filled) as usize),
+                percent,
+                misc::format_storage_unit(sent / 1024),
+                misc::format_storage_unit(total / 1024)
+            );
+            let _ = io::stdout().flush();
+        },
+    );
+    println!();
+
+    let res = misc::ok_or_exit(res);
+    println!("{} {}", "Deployed release".green(), res.release);
+}
@@ -1,71 +1,34 @@
-use std::fmt::Display;
-
 use chrono::Utc;
 use clap::ArgMatches;
 use colored::Colorize;
-use serde::Deserialize;
-use serde_json::json;
-
-use crate::misc::{self, tc};
-
-#[derive(Deserialize)]
-struct InfoInfo {
-    name: String,
-    status: Status,
-    info: Option<Info>,
-    output: Output,
-}
 
-#[derive(Deserialize)]
-struct Info {
-    pid: usize,
-    memory: u32,
-    threads: u32,
-    uptime: u64,
-}
-
-#[derive(Deserialize)]
-struct Output {
-    stdout: String,
-    stderr: String,
-}
-
-#[derive(Deserialize)]
-#[serde(rename_all = "snake_case")]
-pub enum Status {
-    Running,
-    Stoped,
-    Crashed(Option<i32>),
-}
+use crate::misc::{self, t, tc, StatusColor};
 
 pub fn run(args: ArgMatches) {
     let name = args.get_one::<String>("app").unwrap();
 
     // Get host
-    let (host, token) = match misc::host_stuff(&args) {
+    let client = match misc::host_stuff(&args) {
         Some(i) => i,
         None => return,
     };
 
     let now = Utc::now().timestamp() as u64;
-    let raw = misc::deamon_req(
-        "GET",
-        &host,
-        "app/info",
-        Some(json!({ "name": name, "token": token })),
-    )
-    .expect("Error getting data");
-    let body = InfoInfo::deserialize(raw).expect("Invalid data fetched");
+    let body = misc::ok_or_exit(client.app_info(name));
     let stdout = take_lines(body.output.stdout);
     let stderr = take_lines(body.output.stderr);
 
     // UI DESIGN (systemd inspired ofc)
     // ● PlasterBox
-    //   Status: (Running, Stpoed, Crashed)
+    //   Status: (Starting, Running, Stopping, Stpoed, Crashed)
     //   Uptime: 100 hours
     //      Pid: 69
     //  Threads: 3
     //   Memory: 100mb
+    //     Next: 70 (waiting to be ready)
+    // Revision: 1a2b3c4 main "Fix the thing" by Connor Slade (dirty) [2 ahead, 1 behind origin/main]
+    //   Report: ready "Serving 3 clients" (5 seconds ago)
+    //  Metrics: clients=3, queue=0
     //
     // == STDOUT ==
     // ----
@@ -82,7 +45,7 @@ pub fn run(args: ArgMatches) {
         body.status.dot().bold(),
         body.name.magenta().bold()
     );
-    println!("  {} {}", "Status:".blue(), body.status);
+    println!("  {} {}", "Status:".blue(), body.status.name());
     if let Some(i) = body.info {
         if i.uptime != 0 {
             println!(
@@ -95,6 +58,64 @@ pub fn run(args: ArgMatches) {
         println!(" {} {}", "Threads:".blue(), i.threads);
         println!("  {} {}", "Memory:".blue(), i.memory);
     }
+    if let Some(i) = body.next {
+        println!(
+            "    {} {} {}",
+            "Next:".blue(),
+            i.pid,
+            "(waiting to be ready)".yellow()
+        );
+    }
+    if let Some(i) = body.revision {
+        let mut sync = Vec::new();
+        if i.ahead > 0 {
+            sync.push(format!("{} ahead", i.ahead));
+        }
+        if i.behind > 0 {
+            sync.push(format!("{} behind", i.behind));
+        }
+        let sync = match (sync.is_empty(), i.upstream) {
+            (false, Some(upstream)) => format!(" [{} {upstream}]", sync.join(", ")),
+            _ => String::new(),
+        };
+
+        println!(
+            "{} {} {} \"{}\" by {}{}{}",
+            "Revision:".blue(),
+            i.commit.chars().take(7).collect::<String>().magenta(),
+            i.branch.as_deref().unwrap_or("(detached)"),
+            i.message,
+            i.author,
+            t(i.dirty, " (dirty)".yellow(), "".normal()),
+            sync.cyan()
+        );
+    }
+
+    if let Some(i) = body.report {
+        println!(
+            "  {} {}{}{} {}",
+            "Report:".blue(),
+            t(i.ready, "ready".green(), "not ready".yellow()),
+            i.status.map(|x| format!(" \"{x}\"")).unwrap_or_default(),
+            i.main_pid
+                .map(|x| format!(" (main pid {x})"))
+                .unwrap_or_default(),
+            format!(
+                "({} ago)",
+                misc::format_elapsed(now.saturating_sub(i.time as u64))
+            )
+            .dimmed()
+        );
+
+        if !i.metrics.is_empty() {
+            let metrics = i
+                .metrics
+                .iter()
+                .map(|(k, v)| format!("{k}={v}"))
+                .collect::<Vec<_>>();
+            println!(" {} {}", "Metrics:".blue(), metrics.join(", "));
+        }
+    }
 
     println!(
         "\n{}\n{}",
@@ -123,33 +144,3 @@ fn take_lines(inp: String) -> (bool, String) {
             .join("\n"),
     )
 }
-
-impl Status {
-    fn dot(&self) -> String {
-        match self {
-            Self::Running => "●".green(),
-            Self::Stoped => "●".yellow(),
-            Self::Crashed(_) => "●".red(),
-        }
-        .to_string()
-    }
-}
-
-impl Display for Status {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(&match self {
-            Self::Running => "Running".to_owned().green(),
-            Self::Stoped => "Stoped".to_owned().yellow(),
-            Self::Crashed(status) => format!(
-                "Crashed{}",
-                tc(
-                    status.is_some(),
-                    (),
-                    |_| format!(" ({})", status.unwrap()),
-                    |_| "".to_owned()
-                )
-            )
-            .red(),
-        })
-    }
-}
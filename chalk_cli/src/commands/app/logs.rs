@@ -0,0 +1,91 @@
+use std::collections::BTreeMap;
+
+use chalk_client::models::LogLevel;
+use chrono::{offset::TimeZone, Local};
+use clap::ArgMatches;
+use colored::{ColoredString, Colorize};
+use serde_json::Value;
+
+use crate::misc;
+
+pub fn run(args: ArgMatches) {
+    let name = args.get_one::<String>("app").unwrap();
+    let page = *args.get_one::<usize>("page").unwrap_or(&0);
+    let lines = *args.get_one::<usize>("lines").unwrap_or(&50);
+    let target = args.get_one::<String>("target").map(|x| x.as_str());
+
+    let level = match args.get_one::<String>("level") {
+        Some(i) => match LogLevel::from_name(i) {
+            Some(i) => Some(i),
+            None => {
+                println!("{}", "[-] Invalid log level".red());
+                return;
+            }
+        },
+        None => None,
+    };
+
+    let mut fields = BTreeMap::new();
+    for i in args.get_many::<String>("field").into_iter().flatten() {
+        match i.split_once('=') {
+            Some((k, v)) => fields.insert(k.to_owned(), v.to_owned()),
+            None => {
+                println!("{} `{}`", "[-] Fields must be key=value, not".red(), i);
+                return;
+            }
+        };
+    }
+
+    // Get host
+    let client = match misc::host_stuff(&args) {
+        Some(i) => i,
+        None => return,
+    };
+
+    let res = misc::ok_or_exit(client.app_logs(name, page, lines, level, target, fields));
+    if res.logs.is_empty() {
+        println!("{}", "(EMPTY PAGE)".reversed());
+        return;
+    }
+
+    // [2023-02-20] [12:00:00] INFO  my_app::db: Slow query ms=1200
+    for i in res.logs.iter().rev() {
+        let time = Local.timestamp_opt(i.time, 0).unwrap();
+        let target = match &i.target {
+            Some(i) => format!("{}: ", i).dimmed().to_string(),
+            None => String::new(),
+        };
+        let fields = i
+            .fields
+            .iter()
+            .map(|(k, v)| match v {
+                Value::String(v) => format!(" {}={}", k.cyan(), v),
+                v => format!(" {}={}", k.cyan(), v),
+            })
+            .collect::<String>();
+
+        println!(
+            "{} {:5} {}{}{}",
+            time.format("[%Y-%m-%d] [%H:%M:%S]"),
+            level_color(i.level),
+            target,
+            i.message,
+            fields
+        );
+    }
+
+    if res.end {
+        println!("{}", "(START)".reversed());
+    }
+}
+
+fn level_color(level: LogLevel) -> ColoredString {
+    let name = level.to_string().to_uppercase();
+    match level {
+        LogLevel::Trace => name.dimmed(),
+        LogLevel::Debug => name.blue(),
+        LogLevel::Info => name.green(),
+        LogLevel::Warn => name.yellow(),
+        LogLevel::Error => name.red(),
+    }
+}
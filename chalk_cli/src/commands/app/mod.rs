@@ -1,3 +1,10 @@
+pub mod control;
+pub mod create;
+pub mod delete;
+pub mod deploy;
 pub mod info;
+pub mod logs;
+pub mod releases;
+pub mod rollback;
 pub mod start;
 pub mod stop;
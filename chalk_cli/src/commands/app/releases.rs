@@ -0,0 +1,49 @@
+use chrono::{offset::TimeZone, Local};
+use clap::ArgMatches;
+use colored::Colorize;
+
+use crate::misc::{self, t};
+
+pub fn run(args: ArgMatches) {
+    let name = args.get_one::<String>("app").unwrap();
+
+    // Get host
+    let client = match misc::host_stuff(&args) {
+        Some(i) => i,
+        None => return,
+    };
+
+    let res = misc::ok_or_exit(client.app_releases(name));
+
+    // ● PlasterBox (3 releases)
+    //   ├ 1 [2023-02-20 12:00] 1a2b3c4 global token
+    //   └ 2 [2023-02-21 12:00] 5d6e7f8 GitHub webhook (current)
+    println!(
+        "{} {} {}",
+        "●".bold(),
+        name.magenta().bold(),
+        format!("({} releases)", res.releases.len()).cyan()
+    );
+
+    let total = res.releases.len();
+    for (i, e) in res.releases.iter().enumerate() {
+        let time = Local.timestamp_opt(e.time, 0).unwrap();
+        let commit = match &e.commit {
+            Some(i) => i.chars().take(7).collect(),
+            None => e.checksum.chars().take(7).collect::<String>(),
+        };
+        let current = res.current == Some(e.id);
+
+        println!(
+            "  {} {} {} {} {}{}{}{}",
+            t(i + 1 == total, "└", "├"),
+            t(current, e.id.to_string().green(), e.id.to_string().normal()).bold(),
+            time.format("[%Y-%m-%d %H:%M]").to_string().blue(),
+            commit.magenta(),
+            e.uploader,
+            t(e.bundle, " [bundle]".cyan(), "".normal()),
+            t(e.key.is_some(), " [signed]".cyan(), "".normal()),
+            t(current, " (current)".green(), "".normal())
+        );
+    }
+}
@@ -0,0 +1,18 @@
+use clap::ArgMatches;
+use colored::Colorize;
+
+use crate::misc;
+
+pub fn run(args: ArgMatches) {
+    let name = args.get_one::<String>("app").unwrap();
+    let release = args.get_one::<u32>("release").copied();
+
+    // Get host
+    let client = match misc::host_stuff(&args) {
+        Some(i) => i,
+        None => return,
+    };
+
+    let res = misc::ok_or_exit(client.app_rollback(name, release));
+    println!("{} {}", "Switched to release".green(), res.release);
+}
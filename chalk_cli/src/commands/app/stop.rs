@@ -1,6 +1,5 @@
 use clap::ArgMatches;
 use colored::Colorize;
-use serde_json::json;
 
 use crate::misc;
 
@@ -9,27 +8,11 @@ pub fn run(args: ArgMatches) {
     let signal = args.get_one::<String>("signal");
 
     // Get host
-    let (host, token) = match misc::host_stuff(&args) {
+    let client = match misc::host_stuff(&args) {
         Some(i) => i,
         None => return,
     };
 
-    let res = misc::deamon_req(
-        "POST",
-        &host,
-        "app/action",
-        Some(json!({
-            "name": name,
-            "action": "Stop",
-            "signal": signal,
-            "token": token
-        })),
-    )
-    .unwrap();
-
-    if let Some(e) = res.get("error") {
-        println!("{}", format!("Error: `{}`", e.as_str().unwrap()).red());
-    }
-
+    misc::ok_or_exit(client.app_stop(name, signal.map(|x| x.as_str())));
     println!("{}", "Ok".green());
 }
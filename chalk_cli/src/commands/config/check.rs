@@ -0,0 +1,64 @@
+use std::fs;
+use std::process;
+
+use chalk_client::models::{ConfigKind, ProblemLevel};
+use clap::ArgMatches;
+use colored::Colorize;
+
+use crate::misc::{self, t};
+
+pub fn run(args: ArgMatches) {
+    let app = args.get_one::<String>("app");
+    let kind = args
+        .get_one::<String>("kind")
+        .map(|x| t(x == "daemon", ConfigKind::Daemon, ConfigKind::Project));
+    let config = match args.get_one::<String>("path").map(fs::read_to_string) {
+        Some(Ok(i)) => Some(i),
+        Some(Err(e)) => {
+            println!("{} ({})", "[-] Error reading config".red(), e);
+            return;
+        }
+        None => None,
+    };
+
+    // Get host
+    let client = match misc::host_stuff(&args) {
+        Some(i) => i,
+        None => return,
+    };
+
+    let res =
+        misc::ok_or_exit(client.config_check(config.as_deref(), kind, app.map(|x| x.as_str())));
+
+    // ● config.toml (project)
+    //   ✗ [11:7] expected `.`, `=`
+    //   ! `run.command` (binary) does not exist yet
+    let mut errors = false;
+    for i in res.files {
+        let file = args.get_one::<String>("path").unwrap_or(&i.file);
+        let dot = match (i.has_errors(), i.problems.is_empty()) {
+            (true, _) => "●".red(),
+            (false, true) => "●".green(),
+            (false, false) => "●".yellow(),
+        };
+        println!(
+            "{} {} {}",
+            dot.bold(),
+            file.magenta().bold(),
+            format!("({})", i.kind).cyan()
+        );
+
+        for e in &i.problems {
+            match e.level {
+                ProblemLevel::Error => println!("  {} {}", "✗".red(), e.to_string().red()),
+                ProblemLevel::Warning => println!("  {} {}", "!".yellow(), e),
+            }
+        }
+
+        errors |= i.has_errors();
+    }
+
+    if errors {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,25 @@
+use clap::ArgMatches;
+use colored::Colorize;
+
+use crate::misc;
+
+pub fn run(args: ArgMatches) {
+    // Get host
+    let client = match misc::host_stuff(&args) {
+        Some(i) => i,
+        None => return,
+    };
+
+    let res = misc::ok_or_exit(client.config_reload());
+    println!("{}", "Config reloaded".green());
+
+    let lines = [
+        ("Applied:", res.applied.join(", ").green()),
+        ("Needs restart:", res.restart_required.join(", ").red()),
+        ("Loaded:", res.loaded.join(", ").magenta()),
+        ("Unloaded:", res.unloaded.join(", ").magenta()),
+    ];
+    for (name, value) in lines.iter().filter(|x| !x.1.is_empty()) {
+        println!("  {} {}", name.blue(), value);
+    }
+}
@@ -1,5 +1,7 @@
 use std::io::{stdout, Write};
 
+use chalk_client::models::{Log, LogLevel, LogSource, LogsResponse};
+use chalk_client::Client;
 use chrono::{offset::TimeZone, Local};
 use clap::ArgMatches;
 use colored::Colorize;
@@ -13,34 +15,9 @@ use crossterm::{
         EnterAlternateScreen, LeaveAlternateScreen,
     },
 };
-use serde::Deserialize;
-use serde_json::json;
 
 use crate::misc::{self, t};
 
-#[derive(Deserialize)]
-struct LogsInfo {
-    #[serde(skip)]
-    page: usize,
-    logs: Vec<Log>,
-    end: bool,
-}
-
-#[derive(Deserialize)]
-struct Log {
-    text: String,
-    #[serde(rename = "type")]
-    log_type: LogType,
-    time: i64,
-}
-
-#[derive(Deserialize)]
-#[serde(rename_all = "snake_case")]
-pub enum LogType {
-    Error,
-    Info,
-}
-
 pub fn run(args: ArgMatches) {
     // Get args
     let is_basic = args.contains_id("basic");
@@ -48,20 +25,38 @@ pub fn run(args: ArgMatches) {
         .get_one::<usize>("lines")
         .unwrap_or(&size().map(|x| x.1 as usize).unwrap_or(20));
     let mut page = *args.get_one::<usize>("start_page").unwrap_or(&0);
+    let source = args
+        .get_one::<String>("source")
+        .map(|x| LogSource::from_name(x));
+    let level = match args.get_one::<String>("level") {
+        Some(i) => match LogLevel::from_name(i) {
+            Some(i) => Some(i),
+            None => {
+                println!("{}", "[-] Invalid log level".red());
+                return;
+            }
+        },
+        None => None,
+    };
+    let filter = Filter { level, source };
 
     // Get host
-    let (host, token) = match misc::host_stuff(&args) {
+    let client = match misc::host_stuff(&args) {
         Some(i) => i,
         None => return,
     };
 
     if is_basic {
-        basic(get_lines(&host, &token, page, lines, false, None));
+        basic(get_lines(&client, &filter, page, lines, None), page);
         return;
     }
 
     let mut loaded_lines = Vec::new();
-    let info = get_lines(&host, &token, page, lines, false, None);
+    let info = get_lines(&client, &filter, page, lines, None);
+    if info.logs.is_empty() {
+        println!("{}", "(EMPTY PAGE)".reversed());
+        return;
+    }
     let mut end = info.end;
     let mut line: usize = 0;
     loaded_lines.extend(info.logs);
@@ -91,15 +86,7 @@ pub fn run(args: ArgMatches) {
                 .skip(line.saturating_sub(1))
                 .take(t(line == 0, height - 1, height))
         {
-            let time = Local.timestamp_opt(i.time, 0).unwrap();
-            let line = format!("{} {}", time.format("[%Y-%m-%d] [%H:%M:%S]"), i.text);
-            queue!(
-                stdout,
-                Print(i.log_type.colorize(&line)),
-                MoveToColumn(0),
-                MoveDown(1)
-            )
-            .unwrap();
+            queue!(stdout, Print(format_log(i)), MoveToColumn(0), MoveDown(1)).unwrap();
         }
 
         if line + height > loaded_lines.len() + 1 {
@@ -133,7 +120,7 @@ pub fn run(args: ArgMatches) {
 
         if lines + line > (page + 1) * lines && !end {
             page += 1;
-            let info = get_lines(&host, &token, page, lines, false, Some(end_time));
+            let info = get_lines(&client, &filter, page, lines, Some(end_time));
             end = end || info.end;
             loaded_lines.extend(info.logs);
         }
@@ -143,39 +130,41 @@ pub fn run(args: ArgMatches) {
     disable_raw_mode().unwrap();
 }
 
+/// Which logs to show
+struct Filter {
+    level: Option<LogLevel>,
+    source: Option<LogSource>,
+}
+
 fn get_lines(
-    host: &str,
-    token: &str,
+    client: &Client,
+    filter: &Filter,
     page: usize,
     lines: usize,
-    rev: bool,
     time: Option<i64>,
-) -> LogsInfo {
-    let info = misc::deamon_req(
-        "POST",
-        host,
-        "logs",
-        Some(json!({"page": page, "lines": lines, "end_time": time, "rev": rev, "token": token})),
-    )
-    .expect("Error getting data");
-
-    LogsInfo::deserialize(info).expect("Invalid data fetched")
+) -> LogsResponse {
+    misc::ok_or_exit(client.logs(
+        page,
+        lines,
+        time,
+        false,
+        filter.level,
+        filter.source.to_owned(),
+    ))
 }
 
-fn basic(info: LogsInfo) {
+fn basic(info: LogsResponse, page: usize) {
     if info.logs.is_empty() {
         println!("{}", "(EMPTY PAGE)".reversed());
         return;
     }
 
-    if info.page == 0 {
+    if page == 0 {
         println!("{}", "(END)".reversed());
     }
 
     for i in info.logs {
-        let time = Local.timestamp_opt(i.time, 0).unwrap();
-        let line = format!("{} {}", time.format("[%Y-%m-%d] [%H:%M:%S]"), i.text);
-        println!("{}", i.log_type.colorize(line));
+        println!("{}", format_log(&i));
     }
 
     if info.end {
@@ -183,11 +172,24 @@ fn basic(info: LogsInfo) {
     }
 }
 
-impl LogType {
-    fn colorize<T: AsRef<str>>(&self, msg: T) -> String {
-        match self {
-            LogType::Info => msg.as_ref().to_string(),
-            LogType::Error => msg.as_ref().red().to_string(),
-        }
+fn format_log(log: &Log) -> String {
+    let time = Local.timestamp_opt(log.time, 0).unwrap();
+    let source = match &log.source {
+        LogSource::Daemon => String::new(),
+        LogSource::Web => "[WEB] ".to_owned(),
+        LogSource::App(i) => format!("[{i}] "),
+    };
+    let line = format!(
+        "{} {}{}",
+        time.format("[%Y-%m-%d] [%H:%M:%S]"),
+        source,
+        log.text
+    );
+
+    match log.level {
+        LogLevel::Trace | LogLevel::Debug => line.dimmed().to_string(),
+        LogLevel::Info => line,
+        LogLevel::Warn => line.yellow().to_string(),
+        LogLevel::Error => line.red().to_string(),
     }
 }
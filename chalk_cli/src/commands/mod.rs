@@ -1,7 +1,10 @@
 use clap::ArgMatches;
 
 mod app;
+mod config;
 mod logs;
+mod rescan;
+mod sign;
 mod status;
 mod system;
 mod version;
@@ -12,10 +15,22 @@ pub fn run(command: Commands) {
         CommandType::Status => status::run(command.args),
         CommandType::System => system::run(command.args),
         CommandType::Logs => logs::run(command.args),
+        CommandType::Rescan => rescan::run(command.args),
+        CommandType::Sign => sign::run(command.args),
 
         CommandType::AppInfo => app::info::run(command.args),
         CommandType::AppStart => app::start::run(command.args),
         CommandType::AppStop => app::stop::run(command.args),
+        CommandType::AppCreate => app::create::run(command.args),
+        CommandType::AppDelete => app::delete::run(command.args),
+        CommandType::AppDeploy => app::deploy::run(command.args),
+        CommandType::AppReleases => app::releases::run(command.args),
+        CommandType::AppRollback => app::rollback::run(command.args),
+        CommandType::AppControl => app::control::run(command.args),
+        CommandType::AppLogs => app::logs::run(command.args),
+
+        CommandType::ConfigCheck => config::check::run(command.args),
+        CommandType::ConfigReload => config::reload::run(command.args),
     }
 }
 
@@ -30,23 +45,45 @@ pub enum CommandType {
     Status,
     System,
     Logs,
+    Rescan,
+    Sign,
 
     // == APP COMMANDS ==
     AppInfo,
     AppStart,
     AppStop,
+    AppCreate,
+    AppDelete,
+    AppDeploy,
+    AppReleases,
+    AppRollback,
+    AppControl,
+    AppLogs,
+
+    // == CONFIG COMMANDS ==
+    ConfigCheck,
+    ConfigReload,
 }
 
 impl Commands {
     pub fn new(args: (&str, &ArgMatches)) -> Self {
         let sub_cmd = args.0.to_ascii_lowercase();
 
-        if sub_cmd == "app" {
+        if sub_cmd == "app" || sub_cmd == "config" {
             let sub_sub = args.1.subcommand().unwrap();
-            let command_type = match sub_sub.0.to_ascii_lowercase().as_str() {
-                "info" => CommandType::AppInfo,
-                "start" => CommandType::AppStart,
-                "stop" => CommandType::AppStop,
+            let command_type = match (sub_cmd.as_str(), sub_sub.0.to_ascii_lowercase().as_str()) {
+                ("app", "info") => CommandType::AppInfo,
+                ("app", "start") => CommandType::AppStart,
+                ("app", "stop") => CommandType::AppStop,
+                ("app", "create") => CommandType::AppCreate,
+                ("app", "delete") => CommandType::AppDelete,
+                ("app", "deploy") => CommandType::AppDeploy,
+                ("app", "releases") => CommandType::AppReleases,
+                ("app", "rollback") => CommandType::AppRollback,
+                ("app", "control") => CommandType::AppControl,
+                ("app", "logs") => CommandType::AppLogs,
+                ("config", "check") => CommandType::ConfigCheck,
+                ("config", "reload") => CommandType::ConfigReload,
                 _ => unreachable!(),
             };
 
@@ -61,6 +98,8 @@ impl Commands {
             "status" => CommandType::Status,
             "system" => CommandType::System,
             "logs" => CommandType::Logs,
+            "rescan" => CommandType::Rescan,
+            "sign" => CommandType::Sign,
             _ => unreachable!(),
         };
 
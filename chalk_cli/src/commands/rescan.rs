@@ -0,0 +1,22 @@
+use clap::ArgMatches;
+use colored::Colorize;
+
+use crate::misc;
+
+pub fn run(args: ArgMatches) {
+    // Get host
+    let client = match misc::host_stuff(&args) {
+        Some(i) => i,
+        None => return,
+    };
+
+    let res = misc::ok_or_exit(client.rescan());
+    if res.loaded.is_empty() {
+        println!("{}", "No new apps found".yellow());
+        return;
+    }
+
+    for i in res.loaded {
+        println!("{} {}", "Loaded".green(), i.magenta());
+    }
+}
@@ -0,0 +1,90 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+
+use chalk_client::signing;
+use clap::ArgMatches;
+use colored::Colorize;
+
+pub fn run(args: ArgMatches) {
+    let key_path = args.get_one::<String>("key").unwrap();
+
+    if args.get_flag("generate") {
+        generate(key_path);
+        return;
+    }
+
+    let file = args.get_one::<String>("file").unwrap();
+    let key = match fs::read_to_string(key_path) {
+        Ok(i) => i,
+        Err(e) => {
+            println!("{} ({})", "[-] Error reading key".red(), e);
+            return;
+        }
+    };
+    let data = match fs::read(file) {
+        Ok(i) => i,
+        Err(e) => {
+            println!("{} ({})", "[-] Error reading file".red(), e);
+            return;
+        }
+    };
+
+    let (signature, public) = match (signing::sign(&key, &data), signing::public_key(&key)) {
+        (Some(i), Some(j)) => (i, j),
+        _ => {
+            println!(
+                "{}",
+                "[-] Invalid key, expected a hex encoded ed25519 secret key".red()
+            );
+            return;
+        }
+    };
+
+    let out = args
+        .get_one::<String>("out")
+        .cloned()
+        .unwrap_or_else(|| format!("{file}.sig"));
+    if let Err(e) = fs::write(&out, format!("{signature}\n")) {
+        println!("{} ({})", "[-] Error writing signature".red(), e);
+        return;
+    }
+
+    println!("{} {}", "Signed".green(), file.magenta());
+    println!(" {} {}", "├ Signature:".blue(), out);
+    println!(" {} {}", "└ Key:".blue(), public);
+}
+
+/// Makes a new secret key only readable by the current user
+fn generate(path: &str) {
+    if Path::new(path).exists() {
+        println!("{}", format!("[-] `{path}` already exists").red());
+        return;
+    }
+
+    let key = signing::generate_key();
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    // Only readable by the owner
+    #[cfg(unix)]
+    options.mode(0o600);
+
+    let file = options.open(path);
+    if let Err(e) = file.and_then(|mut x| writeln!(x, "{key}")) {
+        println!("{} ({})", "[-] Error writing key".red(), e);
+        return;
+    }
+
+    println!("{} {}", "Generated key".green(), path.magenta());
+    println!(
+        " {} {}",
+        "└ Public key:".blue(),
+        signing::public_key(&key).unwrap()
+    );
+    println!(
+        "{}",
+        "Add the public key to `releases.trusted_keys` in the app config".cyan()
+    );
+}
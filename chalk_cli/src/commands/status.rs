@@ -1,19 +1,11 @@
 use std::fmt::{self, Display, Formatter};
 
+use chalk_client::models::{AppStatus, ProjectStatus, StatusResponse};
 use chrono::Utc;
 use clap::ArgMatches;
 use colored::Colorize;
-use serde::Deserialize;
-use serde_json::json;
 
-use crate::misc::{self, t, tc};
-
-#[derive(Deserialize)]
-struct StatusInfo {
-    version: String,
-    uptime: u64,
-    apps: Vec<Project>,
-}
+use crate::misc::{self, t, StatusColor};
 
 enum SystemStatus {
     Good,
@@ -21,37 +13,21 @@ enum SystemStatus {
     Yikes,
 }
 
-#[derive(Deserialize)]
-struct Project {
-    name: String,
-    status: ProjectState,
-    is_ok: Option<bool>,
-    code: Option<i32>,
-}
-
-#[derive(Deserialize, Eq, PartialEq)]
-#[serde(rename_all = "snake_case")]
-pub enum ProjectState {
-    Running,
-    Stoped,
-    Crashed(Option<i32>),
-}
-
 pub fn run(args: ArgMatches) {
     // Get host
-    let (host, token) = match misc::host_stuff(&args) {
+    let client = match misc::host_stuff(&args) {
         Some(i) => i,
         None => return,
     };
+    let host = client.host();
 
     // Get info from daemon
-    let info = misc::deamon_req("GET", &host, "status", Some(json!({ "token": token }))).unwrap();
-    let info = StatusInfo::deserialize(info).unwrap();
+    let info = misc::ok_or_exit(client.status());
 
     // Extrapalate from data
     let now = Utc::now().timestamp() as u64;
-    let running = app_count(&info.apps, ProjectState::Running);
-    let stoped = app_count(&info.apps, ProjectState::Stoped);
+    let running = info.apps.iter().filter(|x| x.status.is_running()).count();
+    let stoped = app_count(&info.apps, ProjectStatus::Stopped);
     let status = SystemStatus::from(&info);
     let total = info.apps.len();
 
@@ -75,7 +51,7 @@ pub fn run(args: ArgMatches) {
     println!(
         "  {} {}",
         "Uptime:".blue(),
-        misc::format_elapsed(now.saturating_sub(info.uptime)).magenta()
+        misc::format_elapsed(now.saturating_sub(info.uptime as u64)).magenta()
     );
     println!(" {} {}", "Running:".blue(), running.to_string().magenta());
     println!(
@@ -91,44 +67,19 @@ pub fn run(args: ArgMatches) {
             "  {} {} {}",
             t(i + 1 == total, "└", "├"),
             e.status.colorize(&e.name),
-            tc(
-                e.code.is_some(),
-                (),
-                |_| tc(
-                    e.is_ok.unwrap_or(false),
-                    format!("({})", e.code.unwrap()),
-                    |x| x.yellow(),
-                    |x| x.red()
-                )
-                .to_string(),
-                |_| "".to_string()
-            )
+            match e.status {
+                ProjectStatus::Crashed(Some(code)) => format!("({code})").red().to_string(),
+                _ if e.switching => "(switching)".yellow().to_string(),
+                _ => "".to_owned(),
+            }
         );
     }
 }
 
-fn app_count(apps: &[Project], state: ProjectState) -> usize {
-    let state = state.id();
-    apps.iter().filter(|x| x.status.id() == state).count()
-}
-
-impl ProjectState {
-    fn id(&self) -> usize {
-        match self {
-            ProjectState::Running => 0,
-            ProjectState::Stoped => 1,
-            ProjectState::Crashed(_) => 2,
-        }
-    }
-
-    fn colorize(&self, inp: &str) -> String {
-        match self {
-            Self::Running => inp.green(),
-            Self::Stoped => inp.yellow(),
-            Self::Crashed(_) => inp.red(),
-        }
-        .to_string()
-    }
+fn app_count(apps: &[AppStatus], state: ProjectStatus) -> usize {
+    apps.iter()
+        .filter(|x| std::mem::discriminant(&x.status) == std::mem::discriminant(&state))
+        .count()
 }
 
 impl SystemStatus {
@@ -142,9 +93,9 @@ impl SystemStatus {
     }
 }
 
-impl From<&StatusInfo> for SystemStatus {
-    fn from(from: &StatusInfo) -> Self {
-        match app_count(&from.apps, ProjectState::Crashed(None)) {
+impl From<&StatusResponse> for SystemStatus {
+    fn from(from: &StatusResponse) -> Self {
+        match app_count(&from.apps, ProjectStatus::Crashed(None)) {
             0 => Self::Good,
             1 => Self::Degraded,
             _ => Self::Yikes,
@@ -1,58 +1,17 @@
 use clap::ArgMatches;
 use colored::Colorize;
-use serde::Deserialize;
-use serde_json::json;
 
 use crate::misc;
 
-#[derive(Deserialize)]
-struct SystemInfo {
-    disk: Disk,
-    load: Load,
-    memory: Memory,
-    os: Os,
-    processes: u32,
-}
-
-#[derive(Deserialize)]
-struct Disk {
-    free: usize,
-    total: usize,
-}
-
-#[derive(Deserialize)]
-struct Load {
-    #[serde(rename = "1m")]
-    one: f32,
-    #[serde(rename = "5m")]
-    five: f32,
-    #[serde(rename = "15m")]
-    fifteen: f32,
-}
-
-#[derive(Deserialize)]
-struct Memory {
-    free: usize,
-    total: usize,
-}
-
-#[derive(Deserialize)]
-struct Os {
-    release: String,
-    #[serde(rename = "type")]
-    os_type: String,
-}
-
 pub fn run(args: ArgMatches) {
     // Get host
-    let (host, token) = match misc::host_stuff(&args) {
+    let client = match misc::host_stuff(&args) {
         Some(i) => i,
         None => return,
     };
 
     // Get info from daemon
-    let info = misc::deamon_req("GET", &host, "status", Some(json!({ "token": token }))).unwrap();
-    let info = SystemInfo::deserialize(info.get("system").unwrap()).unwrap();
+    let info = misc::ok_or_exit(client.status()).system;
 
     // localhost:3401
     // Processes: 38
@@ -61,7 +20,7 @@ pub fn run(args: ArgMatches) {
     //    Memory: 2G / 8G (25%)
     //        Os: Linux - 4.4.0-19041-Microsoft
 
-    println!("● {}", host.magenta().bold());
+    println!("● {}", client.host().magenta().bold());
     println!(
         "  {} {}",
         "Processes:".blue(),
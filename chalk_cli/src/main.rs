@@ -1,6 +1,5 @@
 mod args;
 mod commands;
-mod error;
 mod misc;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -1,16 +1,15 @@
 use std::fs;
 use std::process;
 
+use chalk_client::models::{ProjectStatus, TokenType};
+use chalk_client::Client;
 use clap::ArgMatches;
 use colored::Colorize;
 use directories::ProjectDirs;
 use lazy_static::lazy_static;
-use serde_json::{json, Value};
-use ureq::Error;
+use serde_json::json;
 use url::Url;
 
-use crate::error::ActionError;
-
 const STORAGE_UNITS: &[&str] = &["KiB", "MiB", "GiB", "TiB", "PiB", "EiB", "ZiB", "YiB"];
 const TIME_UNITS: &[(&str, u16)] = &[
     ("second", 60),
@@ -43,33 +42,15 @@ pub fn tc<T, E>(case: bool, value: T, a: impl Fn(T) -> E, b: impl Fn(T) -> E) ->
     b(value)
 }
 
-pub fn deamon_req(
-    method: &str,
-    host: &str,
-    path: &str,
-    body: Option<Value>,
-) -> Result<Value, ActionError>
-where
-{
-    let req = ureq::request(method, &format!("{host}{path}"));
-    let req = match body {
-        Some(i) => req.send_string(&i.to_string()),
-        None => req.call(),
-    };
-    let req = match req {
-        Ok(res) => res,
-        Err(Error::Status(_, res)) => res,
-        Err(e) => return Err(ActionError::Connect(Box::new(e))),
-    };
-    let data = req.into_string()?;
-    let json = serde_json::from_str::<Value>(&data)?;
-
-    if let Some(i) = json.get("error") {
-        println!("{}", format!("[-] {}", i.as_str().unwrap()).red());
-        process::exit(-1);
+/// Prints the error and exits if a daemon request failed
+pub fn ok_or_exit<T>(res: chalk_client::Result<T>) -> T {
+    match res {
+        Ok(i) => i,
+        Err(e) => {
+            println!("{}", format!("[-] {e}").red());
+            process::exit(-1);
+        }
     }
-
-    Ok(json)
 }
 
 pub fn format_elapsed(secs: u64) -> String {
@@ -88,7 +69,7 @@ pub fn format_elapsed(secs: u64) -> String {
 }
 
 // Kilibytes to hunal readable format
-pub fn format_storage_unit(kb: usize) -> String {
+pub fn format_storage_unit(kb: u64) -> String {
     let mut kb = kb as f64;
     let mut unit = 0;
 
@@ -106,8 +87,8 @@ pub fn format_storage_unit(kb: usize) -> String {
 
 // == HOST STUFF ==
 
-/// Returns (HOST, TOKEN)
-pub fn host_stuff(args: &ArgMatches) -> Option<(String, String)> {
+/// Makes a client for the host and token defined in the args
+pub fn host_stuff(args: &ArgMatches) -> Option<Client> {
     // Get host
     let host = match parse_host(
         args.get_one::<String>("host")
@@ -130,23 +111,22 @@ pub fn host_stuff(args: &ArgMatches) -> Option<(String, String)> {
     };
 
     // Verify Host
-    let req = deamon_req("GET", &host, "ping", Some(json!({ "token": token })));
-    if let Err(i) = req {
-        match i {
-            ActionError::Read(e) => println!("{}\n{}", "[-] Error connecting to host".red(), e),
-            ActionError::Parse(e) => println!("{}\n{}", "[-] Error reading from host".red(), e),
-            ActionError::Connect(e) => println!("{}\n{}", "[-] Error Parsing host json".red(), e),
-        };
-        return None;
-    }
+    let client = Client::new(&host, &token);
+    let ping = match client.ping() {
+        Ok(i) => i,
+        Err(e) => {
+            println!("{}", format!("[-] {e}").red());
+            return None;
+        }
+    };
 
     // If token is global, save it
-    if new && req.unwrap().get("token").unwrap().as_str().unwrap() == "global" {
+    if new && ping.token == TokenType::Global {
         println!("{}", "[*] Saveing token".yellow());
         save_token(&host, &token);
     }
 
-    Some((host, token))
+    Some(client)
 }
 
 pub fn parse_host(inp: &str) -> Result<String, url::ParseError> {
@@ -210,3 +190,50 @@ fn save_token(host: &str, token: &str) {
     )
     .unwrap();
 }
+
+// == STATUS ==
+
+pub trait StatusColor {
+    /// A colored dot for the status (like systemctl)
+    fn dot(&self) -> String;
+    /// Colors some text to match the status
+    fn colorize(&self, inp: &str) -> String;
+    /// The colored status name
+    fn name(&self) -> String;
+}
+
+impl StatusColor for ProjectStatus {
+    fn dot(&self) -> String {
+        self.colorize("●")
+    }
+
+    fn colorize(&self, inp: &str) -> String {
+        match self {
+            Self::Starting | Self::Stopping => inp.cyan(),
+            Self::Running => inp.green(),
+            Self::Stopped => inp.yellow(),
+            Self::Crashed(_) => inp.red(),
+        }
+        .to_string()
+    }
+
+    fn name(&self) -> String {
+        match self {
+            Self::Starting => "Starting".cyan(),
+            Self::Running => "Running".green(),
+            Self::Stopping => "Stopping".cyan(),
+            Self::Stopped => "Stopped".yellow(),
+            Self::Crashed(status) => format!(
+                "Crashed{}",
+                tc(
+                    status.is_some(),
+                    (),
+                    |_| format!(" ({})", status.unwrap()),
+                    |_| "".to_owned()
+                )
+            )
+            .red(),
+        }
+        .to_string()
+    }
+}
@@ -0,0 +1,357 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use ureq::{Agent, AgentBuilder};
+
+use crate::error::{Error, Result};
+use crate::models::*;
+
+/// Blocking client for the chalk daemon api
+pub struct Client {
+    host: String,
+    token: String,
+    agent: Agent,
+}
+
+impl Client {
+    /// Host should be a full url (EX: `http://localhost:3401`)
+    pub fn new(host: impl AsRef<str>, token: impl AsRef<str>) -> Self {
+        Self {
+            host: host.as_ref().trim_end_matches('/').to_owned(),
+            token: token.as_ref().to_owned(),
+            // The daemon closes connections after each response
+            agent: AgentBuilder::new().max_idle_connections(0).build(),
+        }
+    }
+
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    // == Routes ==
+
+    pub fn ping(&self) -> Result<PingResponse> {
+        self.request("GET", "ping", &self.token_request())
+    }
+
+    pub fn status(&self) -> Result<StatusResponse> {
+        self.request("GET", "status", &self.token_request())
+    }
+
+    /// Gets a page of logs, newest first unless `rev` is set.
+    /// Only logs from before `end_time` are counted if its defined.
+    /// Logs below `level` or from other sources than `source` are skipped.
+    pub fn logs(
+        &self,
+        page: usize,
+        lines: usize,
+        end_time: Option<i64>,
+        rev: bool,
+        level: Option<LogLevel>,
+        source: Option<LogSource>,
+    ) -> Result<LogsResponse> {
+        self.request(
+            "POST",
+            "logs",
+            &LogsRequest {
+                token: self.token.to_owned(),
+                page,
+                lines,
+                end_time,
+                rev: Some(rev),
+                level,
+                source,
+            },
+        )
+    }
+
+    /// Loads any new project folders in the daemon's app dir
+    pub fn rescan(&self) -> Result<RescanResponse> {
+        self.request("POST", "rescan", &self.token_request())
+    }
+
+    /// Checks a config with the daemon without loading it.
+    /// With no config the daemon checks all the configs it has on disk.
+    pub fn config_check(
+        &self,
+        config: Option<&str>,
+        kind: Option<ConfigKind>,
+        app: Option<&str>,
+    ) -> Result<ConfigCheckResponse> {
+        self.request(
+            "POST",
+            "config/check",
+            &ConfigCheckRequest {
+                token: self.token.to_owned(),
+                config: config.map(|x| x.to_owned()),
+                kind,
+                app: app.map(|x| x.to_owned()),
+            },
+        )
+    }
+
+    /// Makes the daemon re-read its config, without restarting apps
+    pub fn config_reload(&self) -> Result<ConfigReloadResponse> {
+        self.request("POST", "config/reload", &self.token_request())
+    }
+
+    pub fn app_info(&self, name: &str) -> Result<AppInfoResponse> {
+        self.request(
+            "GET",
+            "app/info",
+            &AppInfoRequest {
+                token: self.token.to_owned(),
+                name: name.to_owned(),
+            },
+        )
+    }
+
+    /// Creates a new project from the text of its `config.toml`
+    pub fn app_create(&self, config: &str) -> Result<AppCreateResponse> {
+        self.request(
+            "POST",
+            "app/create",
+            &AppCreateRequest {
+                token: self.token.to_owned(),
+                config: config.to_owned(),
+            },
+        )
+    }
+
+    /// Stops and deletes a project, optionally moving its folder to the archive
+    pub fn app_delete(&self, name: &str, archive: bool) -> Result<AppDeleteResponse> {
+        self.request(
+            "POST",
+            "app/delete",
+            &AppDeleteRequest {
+                token: self.token.to_owned(),
+                name: name.to_owned(),
+                archive: Some(archive),
+            },
+        )
+    }
+
+    /// Streams a binary, or a bundle to unpack, to the daemon as a new release of an app.
+    /// Uploads go to the daemon's upload port (from [`Client::ping`]), on the same host, where they are streamed to disk.
+    /// `signature` is the hex ed25519 signature of the file, needed if the app has trusted keys.
+    /// `progress` is called with the bytes sent so far and the file size.
+    pub fn app_upload(
+        &self,
+        name: &str,
+        path: &Path,
+        bundle: Option<BundleKind>,
+        signature: Option<&str>,
+        progress: impl FnMut(u64, u64),
+    ) -> Result<AppUploadResponse> {
+        let checksum = sha256_file(path).map_err(Error::File)?;
+        let file = File::open(path).map_err(Error::File)?;
+        let total = file.metadata().map_err(Error::File)?.len();
+
+        let port = match self.ping()?.upload_port {
+            Some(i) => i,
+            None => return Err(Error::Daemon("Daemon does not take uploads".to_owned())),
+        };
+        let mut req = self
+            .agent
+            .post(&format!(
+                "{}/app/upload/{}",
+                with_port(&self.host, port),
+                name
+            ))
+            .set("Authorization", &format!("Bearer {}", self.token))
+            .set("Content-Type", "application/octet-stream")
+            .set("Content-Length", &total.to_string())
+            .set("X-Chalk-Sha256", &checksum);
+        if let Some(i) = bundle {
+            req = req.set("X-Chalk-Bundle", &i.to_string());
+        }
+        if let Some(i) = signature {
+            req = req.set("X-Chalk-Signature", i.trim());
+        }
+
+        let res = req.send(Progress {
+            inner: file,
+            sent: 0,
+            total,
+            callback: progress,
+        });
+        Self::parse_response(res)
+    }
+
+    /// Lists the kept releases of an app
+    pub fn app_releases(&self, name: &str) -> Result<AppReleasesResponse> {
+        self.request(
+            "POST",
+            "app/releases",
+            &AppReleasesRequest {
+                token: self.token.to_owned(),
+                name: name.to_owned(),
+            },
+        )
+    }
+
+    /// Switches an app back to an older release, the previous one if `release` is None
+    pub fn app_rollback(&self, name: &str, release: Option<u32>) -> Result<AppRollbackResponse> {
+        self.request(
+            "POST",
+            "app/rollback",
+            &AppRollbackRequest {
+                token: self.token.to_owned(),
+                name: name.to_owned(),
+                release,
+            },
+        )
+    }
+
+    /// Runs a `system` on the app's `RemoteControl` through the daemon
+    pub fn app_control(&self, name: &str, action: &str, data: Value) -> Result<AppControlResponse> {
+        self.request(
+            "POST",
+            "app/control",
+            &AppControlRequest {
+                token: self.token.to_owned(),
+                name: name.to_owned(),
+                action: action.to_owned(),
+                data,
+            },
+        )
+    }
+
+    /// Gets a page of an app's structured logs, newest first.
+    /// Records below `level` or not matching the `target` prefix and `fields` are skipped.
+    pub fn app_logs(
+        &self,
+        name: &str,
+        page: usize,
+        lines: usize,
+        level: Option<LogLevel>,
+        target: Option<&str>,
+        fields: BTreeMap<String, String>,
+    ) -> Result<AppLogsResponse> {
+        self.request(
+            "POST",
+            "app/logs",
+            &AppLogsRequest {
+                token: self.token.to_owned(),
+                name: name.to_owned(),
+                page,
+                lines,
+                level,
+                target: target.map(|x| x.to_owned()),
+                fields,
+            },
+        )
+    }
+
+    /// Makes an action request for an app with this clients token.
+    /// Send it with [`Client::app_action`].
+    pub fn action(&self, name: &str, action: ActionType) -> AppActionRequest {
+        AppActionRequest::new(self.token.to_owned(), name.to_owned(), action)
+    }
+
+    pub fn app_action(&self, action: &AppActionRequest) -> Result<ActionResponse> {
+        self.request("POST", "app/action", action)
+    }
+
+    pub fn app_start(&self, name: &str) -> Result<ActionResponse> {
+        self.app_action(&self.action(name, ActionType::Start))
+    }
+
+    pub fn app_stop(&self, name: &str, signal: Option<&str>) -> Result<ActionResponse> {
+        let mut action = self.action(name, ActionType::Stop);
+        action.signal = signal.map(|x| x.to_owned());
+        self.app_action(&action)
+    }
+
+    // == Raw ==
+
+    /// Sends a json body to the daemon and parses the response.
+    /// Responses with an `error` field are turned into [`Error::Daemon`].
+    pub fn request<T: Serialize, R: DeserializeOwned>(
+        &self,
+        method: &str,
+        path: &str,
+        body: &T,
+    ) -> Result<R> {
+        let res = self
+            .agent
+            .request(method, &format!("{}/{}", self.host, path))
+            .send_string(&serde_json::to_string(body)?);
+        Self::parse_response(res)
+    }
+
+    fn parse_response<R: DeserializeOwned>(
+        res: std::result::Result<ureq::Response, ureq::Error>,
+    ) -> Result<R> {
+        let res = match res {
+            Ok(res) => res,
+            Err(ureq::Error::Status(_, res)) => res,
+            Err(e) => return Err(e.into()),
+        };
+
+        let json = serde_json::from_str::<Value>(&res.into_string()?)?;
+        if let Some(i) = json.get("error") {
+            return Err(Error::Daemon(match i.as_str() {
+                Some(i) => i.to_owned(),
+                None => i.to_string(),
+            }));
+        }
+
+        Ok(serde_json::from_value(json)?)
+    }
+
+    fn token_request(&self) -> TokenRequest {
+        TokenRequest {
+            token: self.token.to_owned(),
+        }
+    }
+}
+
+/// Reader that reports how much of it has been read
+struct Progress<T: Read, F: FnMut(u64, u64)> {
+    inner: T,
+    sent: u64,
+    total: u64,
+    callback: F,
+}
+
+impl<T: Read, F: FnMut(u64, u64)> Read for Progress<T, F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.sent += read as u64;
+        (self.callback)(self.sent, self.total);
+        Ok(read)
+    }
+}
+
+/// Swaps the port of a url (EX: `http://localhost:3401` -> `http://localhost:3402`)
+fn with_port(url: &str, port: u16) -> String {
+    let (scheme, rest) = url.split_once("://").unwrap_or(("http", url));
+    let (authority, path) = match rest.find('/') {
+        Some(i) => rest.split_at(i),
+        None => (rest, ""),
+    };
+
+    // Ipv6 hosts are in brackets, and have colons of their own
+    let host = match authority.rsplit_once(':') {
+        Some((host, port)) if !port.contains(']') => host,
+        _ => authority,
+    };
+    format!("{scheme}://{host}:{port}{path}")
+}
+
+fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher)?;
+    Ok(hex::encode(hasher.finalize()))
+}
@@ -0,0 +1,146 @@
+//! HMAC signatures for the control requests the daemon sends to apps, and the reports apps send back.
+//! The signature is a hex encoded HMAC-SHA256 of `{domain}\n{timestamp}\n{nonce}\n{body}`, keyed with the app's interface secret.
+//! The domain is `chalk-control` or `chalk-report` (see [`Direction`]), so a request signed one way is never valid the other way.
+
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use hmac::{Hmac, Mac};
+use rand_core::{OsRng, RngCore};
+use sha2::Sha256;
+
+/// Env var with the path of the file holding the app's key, which is only readable by its user
+pub const KEY_FILE_ENV: &str = "CHALK_KEY_FILE";
+/// Env var with the daemon url an app sends its reports to
+pub const REPORT_URL_ENV: &str = "CHALK_REPORT_URL";
+
+pub const TIMESTAMP_HEADER: &str = "X-Chalk-Timestamp";
+pub const NONCE_HEADER: &str = "X-Chalk-Nonce";
+pub const SIGNATURE_HEADER: &str = "X-Chalk-Hmac";
+
+/// Seconds a request's timestamp can be away from the receiver's clock
+pub const MAX_AGE: u64 = 60;
+
+/// Which way a signed request goes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// From the daemon to the app's `RemoteControl`
+    Control,
+    /// From the app to the daemon's report route
+    Report,
+}
+
+/// Headers to send with a signed request
+pub struct SignedHeaders {
+    pub timestamp: String,
+    pub nonce: String,
+    pub signature: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerifyError {
+    /// Timestamp is not a number
+    InvalidTimestamp,
+    /// Timestamp is outside of [`MAX_AGE`]
+    Expired,
+    InvalidSignature,
+}
+
+/// Nonces of recently verified requests, to reject replays
+#[derive(Debug, Default)]
+pub struct Nonces(Mutex<HashMap<String, u64>>);
+
+/// Signs a body with a new timestamp and nonce
+pub fn sign(key: &str, direction: Direction, body: &[u8]) -> SignedHeaders {
+    let timestamp = now().to_string();
+    let mut nonce = [0; 16];
+    OsRng.fill_bytes(&mut nonce);
+    let nonce = hex::encode(nonce);
+
+    SignedHeaders {
+        signature: hex::encode(
+            mac(key, direction, &timestamp, &nonce, body)
+                .finalize()
+                .into_bytes(),
+        ),
+        timestamp,
+        nonce,
+    }
+}
+
+/// Checks the freshness and signature of a request, in constant time.
+/// Returns the timestamp, callers still have to reject nonces they have already seen within [`MAX_AGE`].
+pub fn verify(
+    key: &str,
+    direction: Direction,
+    timestamp: &str,
+    nonce: &str,
+    signature: &str,
+    body: &[u8],
+) -> Result<u64, VerifyError> {
+    let time = timestamp
+        .parse::<u64>()
+        .map_err(|_| VerifyError::InvalidTimestamp)?;
+    if now().abs_diff(time) > MAX_AGE {
+        return Err(VerifyError::Expired);
+    }
+
+    let signature = hex::decode(signature.trim()).map_err(|_| VerifyError::InvalidSignature)?;
+    mac(key, direction, timestamp, nonce, body)
+        .verify_slice(&signature)
+        .map_err(|_| VerifyError::InvalidSignature)?;
+
+    Ok(time)
+}
+
+/// Current unix time in seconds
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|x| x.as_secs())
+        .unwrap_or_default()
+}
+
+impl Nonces {
+    /// Records the nonce of a verified request, false if it was already used
+    pub fn insert(&self, nonce: &str, time: u64) -> bool {
+        let now = now();
+        let mut nonces = self.0.lock().unwrap();
+        // Nonces only have to be kept as long as their request is fresh
+        nonces.retain(|_, x| now.abs_diff(*x) <= MAX_AGE);
+        nonces.insert(nonce.to_owned(), time).is_none()
+    }
+}
+
+impl Display for VerifyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::InvalidTimestamp => "Invalid Timestamp",
+            Self::Expired => "Request Expired",
+            Self::InvalidSignature => "Invalid Signature",
+        })
+    }
+}
+
+impl Direction {
+    fn domain(&self) -> &'static [u8] {
+        match self {
+            Self::Control => b"chalk-control",
+            Self::Report => b"chalk-report",
+        }
+    }
+}
+
+fn mac(key: &str, direction: Direction, timestamp: &str, nonce: &str, body: &[u8]) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key.as_bytes()).unwrap();
+    mac.update(direction.domain());
+    mac.update(b"\n");
+    mac.update(timestamp.as_bytes());
+    mac.update(b"\n");
+    mac.update(nonce.as_bytes());
+    mac.update(b"\n");
+    mac.update(body);
+    mac
+}
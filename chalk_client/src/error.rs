@@ -0,0 +1,51 @@
+use std::error;
+use std::fmt::{self, Display, Formatter};
+use std::io;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    /// Error connecting to the daemon
+    Connect(Box<ureq::Error>),
+    /// Error reading the response body
+    Read(io::Error),
+    /// The response was not the json we expected
+    Parse(serde_json::Error),
+    /// The daemon responded with an error message
+    Daemon(String),
+    /// Error reading a local file to send
+    File(io::Error),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Connect(e) => write!(f, "Error connecting to host: {e}"),
+            Error::Read(e) => write!(f, "Error reading from host: {e}"),
+            Error::Parse(e) => write!(f, "Error parsing host json: {e}"),
+            Error::Daemon(e) => f.write_str(e),
+            Error::File(e) => write!(f, "Error reading file: {e}"),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+impl From<ureq::Error> for Error {
+    fn from(from: ureq::Error) -> Self {
+        Self::Connect(Box::new(from))
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(from: io::Error) -> Self {
+        Self::Read(from)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(from: serde_json::Error) -> Self {
+        Self::Parse(from)
+    }
+}
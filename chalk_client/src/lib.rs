@@ -0,0 +1,21 @@
+//! Types and a blocking client for talking to the chalk daemon.
+//!
+//! The request / response models in [`models`] are shared with the daemon.
+//! The [`Client`] is behind the default `client` feature,
+//! the release signing helpers in [`signing`] are behind the `signing` feature,
+//! and the control request signing in [`control`] is behind the `control` feature.
+
+#[cfg(feature = "control")]
+pub mod control;
+pub mod models;
+#[cfg(feature = "signing")]
+pub mod signing;
+
+#[cfg(feature = "client")]
+mod client;
+#[cfg(feature = "client")]
+mod error;
+#[cfg(feature = "client")]
+pub use client::Client;
+#[cfg(feature = "client")]
+pub use error::{Error, Result};
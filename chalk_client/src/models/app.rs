@@ -0,0 +1,337 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+
+use serde_derive::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{LogLevel, LogRecord, ProjectStatus};
+
+// == Info ==
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AppInfoRequest {
+    pub token: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AppInfoResponse {
+    pub name: String,
+    pub status: ProjectStatus,
+    pub output: Output,
+    pub info: Option<ProcessInfo>,
+    /// New instance waiting to be switched to during a blue/green deploy
+    #[serde(default)]
+    pub next: Option<ProcessInfo>,
+    /// What is checked out in the app's repo, if it has one
+    #[serde(default)]
+    pub revision: Option<Revision>,
+    /// What the app last reported about itself since it was started
+    #[serde(default)]
+    pub report: Option<AppReport>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Revision {
+    /// Hash of the checked out commit
+    pub commit: String,
+    /// Checked out branch, None if the head is detached
+    pub branch: Option<String>,
+    /// First line of the commit message
+    pub message: String,
+    pub author: String,
+    /// Commit timestamp
+    pub time: i64,
+    /// If tracked files have uncommitted changes
+    pub dirty: bool,
+    /// Remote branch the branch tracks (EX: origin/main)
+    pub upstream: Option<String>,
+    /// Commits not in the upstream, as of the last fetch
+    pub ahead: usize,
+    /// Commits in the upstream that are not checked out, as of the last fetch
+    pub behind: usize,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Output {
+    pub stdout: String,
+    pub stderr: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ProcessInfo {
+    pub pid: i32,
+    /// Total program size in pages
+    pub memory: usize,
+    pub threads: i32,
+    /// Start timestamp
+    pub uptime: u64,
+}
+
+// == Action ==
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AppActionRequest {
+    // == Required ==
+    pub token: String,
+    pub name: String,
+    pub action: ActionType,
+
+    // == Stop action ==
+    pub signal: Option<String>,
+
+    // == Update action ==
+    /// Binary data (BASE64(GZIP(RAW)))
+    pub data: Option<String>,
+    /// Git origin (EX: origin)
+    pub remote: Option<String>,
+    /// Git branch to pull from
+    pub branch: Option<String>,
+    /// Commit / Tag to checkout
+    pub checkout: Option<String>,
+    /// Discard local changes in the app's repo
+    pub force: Option<bool>,
+    /// Hex encoded ed25519 signature of the raw binary in `data`
+    #[serde(default)]
+    pub signature: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ActionType {
+    Stop,
+    Start,
+    Update,
+    Reload,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ActionResponse {
+    pub status: String,
+}
+
+// == Create / Delete ==
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AppCreateRequest {
+    pub token: String,
+    /// The project's `config.toml`
+    pub config: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AppCreateResponse {
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AppDeleteRequest {
+    pub token: String,
+    pub name: String,
+    /// Move the project folder to the archive instead of deleting it
+    pub archive: Option<bool>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AppDeleteResponse {
+    /// Where the project was archived to
+    pub archive: Option<String>,
+}
+
+// == Upload ==
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AppUploadResponse {
+    /// The release made from the upload
+    pub release: u32,
+}
+
+/// Archive formats that can be deployed as a whole folder.
+/// Sent in the `X-Chalk-Bundle` header of an upload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BundleKind {
+    TarGz,
+    Zip,
+}
+
+// == Releases ==
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AppReleasesRequest {
+    pub token: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AppReleasesResponse {
+    /// The release `current` points to
+    pub current: Option<u32>,
+    /// Kept releases, oldest first
+    pub releases: Vec<Release>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Release {
+    pub id: u32,
+    /// Commit the release was built from
+    pub commit: Option<String>,
+    /// What made the release (EX: `app token from 127.0.0.1`, `github webhook`)
+    pub uploader: String,
+    /// SHA-256 of the binary or bundle
+    pub checksum: String,
+    /// Creation timestamp
+    pub time: i64,
+    /// If the release is an unpacked bundle rather than a single binary
+    #[serde(default)]
+    pub bundle: bool,
+    /// Public key of the trusted key that signed the release
+    #[serde(default)]
+    pub key: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AppRollbackRequest {
+    pub token: String,
+    pub name: String,
+    /// Release to switch to, the one before `current` if not set
+    pub release: Option<u32>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AppRollbackResponse {
+    pub release: u32,
+}
+
+// == Control ==
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AppControlRequest {
+    pub token: String,
+    pub name: String,
+    /// Name of the `system` on the app's `RemoteControl`
+    pub action: String,
+    #[serde(default)]
+    pub data: Value,
+}
+
+/// What the app responded with
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AppControlResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+// == Logs ==
+
+/// Gets the structured logs of an app, newest first
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AppLogsRequest {
+    pub token: String,
+    pub name: String,
+    pub page: usize,
+    pub lines: usize,
+    /// Only records at or above this level
+    pub level: Option<LogLevel>,
+    /// Only records with a target starting with this
+    pub target: Option<String>,
+    /// Only records with these fields, non string values are compared as JSON
+    #[serde(default)]
+    pub fields: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AppLogsResponse {
+    pub logs: Vec<LogRecord>,
+    pub end: bool,
+}
+
+// == Report ==
+
+/// Sent by an app to its `/app/report/{name}` url, signed with its interface secret.
+/// Fields that are not set keep their last reported value.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct AppReportRequest {
+    /// If the app is ready to serve (like sd_notify's `READY=1`)
+    #[serde(default)]
+    pub ready: Option<bool>,
+    /// Short human readable status (EX: Serving 3 clients)
+    #[serde(default)]
+    pub status: Option<String>,
+    /// Added to the previously reported metrics
+    #[serde(default)]
+    pub metrics: BTreeMap<String, f64>,
+    #[serde(default)]
+    pub event: Option<AppEvent>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AppEvent {
+    /// Asks the daemon to restart the app
+    Restart,
+    /// The app is reloading its config
+    Reloading,
+}
+
+/// What an app has reported about itself, through its report url or notify socket
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct AppReport {
+    pub ready: bool,
+    pub status: Option<String>,
+    /// Pid the app said is its main process (sd_notify's `MAINPID=`)
+    #[serde(default)]
+    pub main_pid: Option<u32>,
+    pub metrics: BTreeMap<String, f64>,
+    /// Timestamp of the last report
+    pub time: i64,
+}
+
+impl AppActionRequest {
+    pub fn new(token: String, name: String, action: ActionType) -> Self {
+        Self {
+            token,
+            name,
+            action,
+            signal: None,
+            data: None,
+            remote: None,
+            branch: None,
+            checkout: None,
+            force: None,
+            signature: None,
+        }
+    }
+}
+
+impl BundleKind {
+    /// Gets the kind from a header value or file extension
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase();
+        if name.ends_with("tar.gz") || name.ends_with("tgz") {
+            Some(Self::TarGz)
+        } else if name.ends_with("zip") {
+            Some(Self::Zip)
+        } else {
+            None
+        }
+    }
+}
+
+impl Display for BundleKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::TarGz => "tar.gz",
+            Self::Zip => "zip",
+        })
+    }
+}
+
+impl Display for ActionType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Reload => "reload",
+            Self::Start => "start",
+            Self::Stop => "stop",
+            Self::Update => "update",
+        })
+    }
+}
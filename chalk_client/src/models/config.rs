@@ -0,0 +1,109 @@
+use std::fmt::{self, Display, Formatter};
+
+use serde_derive::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ConfigCheckRequest {
+    pub token: String,
+    /// Text of the config to check.
+    /// If not defined the daemon config and all app configs on disk are checked.
+    pub config: Option<String>,
+    /// Detected from the config if not defined
+    pub kind: Option<ConfigKind>,
+    /// The app a project config is for.
+    /// If not defined it is checked as a new app.
+    pub app: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ConfigCheckResponse {
+    pub files: Vec<ConfigReport>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ConfigReport {
+    /// Path to the config file or `<request>` for a sent config
+    pub file: String,
+    pub kind: ConfigKind,
+    pub problems: Vec<ConfigProblem>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ConfigProblem {
+    pub level: ProblemLevel,
+    pub message: String,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ConfigReloadResponse {
+    /// Changed settings that were applied
+    pub applied: Vec<String>,
+    /// Changed settings that need a daemon restart to apply
+    pub restart_required: Vec<String>,
+    /// Apps that were loaded
+    pub loaded: Vec<String>,
+    /// Apps that were unloaded as their folder is gone
+    pub unloaded: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigKind {
+    Daemon,
+    Project,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProblemLevel {
+    /// The config would fail to load or the app would fail to start
+    Error,
+    /// The config loads but something looks off
+    Warning,
+}
+
+impl ConfigReport {
+    pub fn has_errors(&self) -> bool {
+        self.problems.iter().any(|x| x.level == ProblemLevel::Error)
+    }
+}
+
+impl ConfigProblem {
+    pub fn error(message: impl Into<String>) -> Self {
+        Self {
+            level: ProblemLevel::Error,
+            message: message.into(),
+            line: None,
+            column: None,
+        }
+    }
+
+    pub fn warning(message: impl Into<String>) -> Self {
+        Self {
+            level: ProblemLevel::Warning,
+            message: message.into(),
+            line: None,
+            column: None,
+        }
+    }
+}
+
+impl Display for ConfigProblem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => write!(f, "[{line}:{column}] {}", self.message),
+            _ => f.write_str(&self.message),
+        }
+    }
+}
+
+impl Display for ConfigKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ConfigKind::Daemon => "daemon",
+            ConfigKind::Project => "project",
+        })
+    }
+}
@@ -0,0 +1,129 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+
+use serde_derive::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LogsRequest {
+    pub token: String,
+    pub page: usize,
+    pub lines: usize,
+    pub end_time: Option<i64>,
+    pub rev: Option<bool>,
+    /// Only entries at or above this level
+    #[serde(default)]
+    pub level: Option<LogLevel>,
+    #[serde(default)]
+    pub source: Option<LogSource>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LogsResponse {
+    pub logs: Vec<Log>,
+    pub end: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Log {
+    pub level: LogLevel,
+    pub source: LogSource,
+    pub text: String,
+    pub time: i64,
+}
+
+/// What part of the daemon a log entry came from
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogSource {
+    Daemon,
+    /// Requests to the api
+    Web,
+    /// Something happening to an app, by its name
+    App(String),
+}
+
+impl LogSource {
+    /// Parses `daemon`, `web` or an app name
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "daemon" => Self::Daemon,
+            "web" => Self::Web,
+            i => Self::App(i.to_owned()),
+        }
+    }
+}
+
+impl Display for LogSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            LogSource::Daemon => f.write_str("daemon"),
+            LogSource::Web => f.write_str("web"),
+            LogSource::App(i) => f.write_str(i),
+        }
+    }
+}
+
+// == App Logs ==
+
+/// Level of a daemon log entry or structured app log, ordered from least to most severe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+/// A JSON log line written by an app, see the daemon's `project::records` for the accepted formats
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LogRecord {
+    /// When the daemon read the line
+    pub time: i64,
+    pub level: LogLevel,
+    pub message: String,
+    /// Module or logger the record came from
+    pub target: Option<String>,
+    /// Any other keys of the record
+    pub fields: BTreeMap<String, Value>,
+}
+
+impl LogLevel {
+    /// Parses the level names used by common loggers (EX: `INFO`, `warning`, `err`)
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name.to_ascii_lowercase().as_str() {
+            "trace" => Self::Trace,
+            "debug" => Self::Debug,
+            "info" | "information" | "notice" => Self::Info,
+            "warn" | "warning" => Self::Warn,
+            "error" | "err" | "fatal" | "critical" | "crit" => Self::Error,
+            _ => return None,
+        })
+    }
+
+    /// Parses the numeric levels used by bunyan and pino
+    pub fn from_number(level: u64) -> Option<Self> {
+        Some(match level {
+            10 => Self::Trace,
+            20 => Self::Debug,
+            30 => Self::Info,
+            40 => Self::Warn,
+            50.. => Self::Error,
+            _ => return None,
+        })
+    }
+}
+
+impl Display for LogLevel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+        f.write_str(match self {
+            LogLevel::Trace => "trace",
+            LogLevel::Debug => "debug",
+            LogLevel::Info => "info",
+            LogLevel::Warn => "warn",
+            LogLevel::Error => "error",
+        })
+    }
+}
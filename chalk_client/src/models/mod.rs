@@ -0,0 +1,48 @@
+use serde_derive::{Deserialize, Serialize};
+
+mod app;
+mod config;
+mod logs;
+mod ping;
+mod rescan;
+mod status;
+pub use app::*;
+pub use config::*;
+pub use logs::*;
+pub use ping::*;
+pub use rescan::*;
+pub use status::*;
+
+/// Body sent by the daemon when a request fails
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+}
+
+/// Request body for routes that only need a token
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TokenRequest {
+    pub token: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProjectStatus {
+    /// Started, waiting for the app to send `READY=1` to its notify socket
+    Starting,
+    Running,
+    /// The app sent `STOPPING=1` to its notify socket
+    Stopping,
+    Stopped,
+    Crashed(Option<i32>),
+}
+
+impl ProjectStatus {
+    /// If the process is alive, even if it is still starting or already stopping
+    pub fn is_running(&self) -> bool {
+        matches!(
+            self,
+            ProjectStatus::Starting | ProjectStatus::Running | ProjectStatus::Stopping
+        )
+    }
+}
@@ -0,0 +1,24 @@
+use serde_derive::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PingResponse {
+    pub version: String,
+    pub token: TokenType,
+    /// Port the daemon takes release uploads on
+    #[serde(default)]
+    pub upload_port: Option<u16>,
+}
+
+/// What a token is allowed to access
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenType {
+    /// The daemon's global token
+    Global,
+
+    /// A project's token
+    Scoped,
+
+    /// Not a valid token
+    Any,
+}
@@ -0,0 +1,7 @@
+use serde_derive::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RescanResponse {
+    /// Names of the newly loaded projects
+    pub loaded: Vec<String>,
+}
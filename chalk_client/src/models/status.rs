@@ -0,0 +1,61 @@
+use serde_derive::{Deserialize, Serialize};
+
+use super::{Log, ProjectStatus};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct StatusResponse {
+    pub version: String,
+    pub uptime: i64,
+    pub system: SystemInfo,
+    pub apps: Vec<AppStatus>,
+    pub logs: Vec<Log>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AppStatus {
+    pub name: String,
+    pub status: ProjectStatus,
+    /// A new instance is being started next to the running one
+    #[serde(default)]
+    pub switching: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SystemInfo {
+    pub disk: Disk,
+    pub memory: Memory,
+    pub load: Load,
+    pub os: Os,
+    pub processes: u64,
+}
+
+/// Disk usage in KiB
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Disk {
+    pub total: u64,
+    pub free: u64,
+}
+
+/// Memory usage in KiB
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Memory {
+    pub total: u64,
+    pub free: u64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Load {
+    #[serde(rename = "1m")]
+    pub one: f64,
+    #[serde(rename = "5m")]
+    pub five: f64,
+    #[serde(rename = "15m")]
+    pub fifteen: f64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Os {
+    #[serde(rename = "type")]
+    pub os_type: String,
+    pub release: String,
+}
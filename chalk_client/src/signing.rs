@@ -0,0 +1,75 @@
+//! Detached ed25519 signatures for release artifacts.
+//! Keys and signatures are hex encoded, secret keys being the 32 byte seed.
+
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use rand_core::OsRng;
+
+/// Makes a new hex encoded secret key
+pub fn generate_key() -> String {
+    hex::encode(SigningKey::generate(&mut OsRng).to_bytes())
+}
+
+/// Gets the hex encoded public key of a secret key
+pub fn public_key(secret: &str) -> Option<String> {
+    Some(hex::encode(signing_key(secret)?.verifying_key().to_bytes()))
+}
+
+/// Signs data with a secret key, returning the hex encoded signature
+pub fn sign(secret: &str, data: &[u8]) -> Option<String> {
+    Some(hex::encode(signing_key(secret)?.sign(data).to_bytes()))
+}
+
+/// Checks a signature against a list of public keys, returning the key that made it
+pub fn verify<'a>(keys: &'a [String], data: &[u8], signature: &str) -> Option<&'a String> {
+    let signature = hex::decode(signature.trim()).ok()?;
+    let signature = Signature::from_slice(&signature).ok()?;
+
+    keys.iter().find(|x| {
+        parse_public_key(x)
+            .map(|x| x.verify_strict(data, &signature).is_ok())
+            .unwrap_or(false)
+    })
+}
+
+/// Decodes a hex encoded public key
+pub fn parse_public_key(key: &str) -> Option<VerifyingKey> {
+    let bytes = hex::decode(key.trim()).ok()?.try_into().ok()?;
+    VerifyingKey::from_bytes(&bytes).ok()
+}
+
+fn signing_key(secret: &str) -> Option<SigningKey> {
+    let bytes = hex::decode(secret.trim()).ok()?.try_into().ok()?;
+    Some(SigningKey::from_bytes(&bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signatures_verify() {
+        let secret = generate_key();
+        let keys = [public_key(&secret).unwrap()];
+        let signature = sign(&secret, b"release").unwrap();
+
+        assert_eq!(verify(&keys, b"release", &signature), Some(&keys[0]));
+    }
+
+    #[test]
+    fn changed_data_fails() {
+        let secret = generate_key();
+        let keys = [public_key(&secret).unwrap()];
+        let signature = sign(&secret, b"release").unwrap();
+
+        assert_eq!(verify(&keys, b"releasf", &signature), None);
+        assert_eq!(verify(&keys, b"release ", &signature), None);
+    }
+
+    #[test]
+    fn other_keys_fail() {
+        let keys = [public_key(&generate_key()).unwrap()];
+        let signature = sign(&generate_key(), b"release").unwrap();
+
+        assert_eq!(verify(&keys, b"release", &signature), None);
+    }
+}
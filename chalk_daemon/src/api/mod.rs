@@ -1,14 +1,17 @@
 use std::sync::Arc;
 use std::thread;
 
-use afire::{Content, Response, Server};
+use afire::{middleware::Middleware, Content, Response, Server};
 use serde_json::json;
 
-use crate::{App, LogType};
+use crate::{App, LogLevel, LogSource};
 
+mod request_log;
 mod routes;
+mod upload;
 
 pub fn start(app: Arc<App>) {
+    upload::start(app.clone());
     thread::Builder::new()
         .name("API".into())
         .spawn(|| _start(app))
@@ -17,12 +20,19 @@ pub fn start(app: Arc<App>) {
 
 fn _start(app: Arc<App>) {
     // Create Server
-    let mut server = Server::<()>::new(app.config.api.host.as_str(), app.config.api.port);
+    let config = app.config.read();
+    let (host, port, workers) = (
+        config.api.host.to_owned(),
+        config.api.port,
+        config.api.workers,
+    );
+    drop(config);
+    let mut server = Server::<()>::new(host.as_str(), port);
 
     // Change error handler to use json
     let error_app = app.clone();
     server.error_handler(move |_state, _req, err| {
-        error_app.log(LogType::Error, format!("[WEB] {err}"));
+        error_app.log_from(LogSource::Web, LogLevel::Error, &err);
         Response::new()
             .status(500)
             .text(json!({ "error": err }))
@@ -31,7 +41,8 @@ fn _start(app: Arc<App>) {
 
     // Add routes
     routes::attach(&mut server, app.clone());
+    request_log::RequestLog(app.clone()).attach(&mut server);
 
     // Start API
-    server.start_threaded(app.config.api.workers).unwrap();
+    server.start_threaded(workers).unwrap();
 }
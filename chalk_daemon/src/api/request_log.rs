@@ -0,0 +1,32 @@
+use std::sync::Arc;
+
+use afire::{middleware::Middleware, Request, Response};
+
+use crate::{misc::RealIp, App, LogLevel, LogSource};
+
+/// Logs every handled request at `Debug`, and more about it at `Trace`
+pub struct RequestLog(pub Arc<App>);
+
+impl Middleware for RequestLog {
+    fn end(&self, req: &Request, res: &Response) {
+        let ip = req.real_ip();
+        self.0.log_from(
+            LogSource::Web,
+            LogLevel::Debug,
+            format!("[{ip}] {} {} ({})", req.method, req.path, res.status.code()),
+        );
+
+        // Values can hold tokens or signatures, so only the header names
+        let headers = req
+            .headers
+            .iter()
+            .map(|x| x.name.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        self.0.log_from(
+            LogSource::Web,
+            LogLevel::Trace,
+            format!("[{ip}] {} byte body, headers: {headers}", req.body.len()),
+        );
+    }
+}
@@ -1,71 +1,42 @@
-use std::fmt::Display;
 use std::fs;
 use std::io::{Cursor, Read};
-use std::path::Path;
 use std::str::FromStr;
 use std::sync::Arc;
 
-use afire::{Content, Method, Response, Server};
+use afire::{Method, Server};
 use base64::engine::general_purpose::STANDARD;
 use base64::Engine;
+use chalk_client::models::{ActionResponse, ActionType, AppActionRequest, ProblemLevel};
 use flate2::read::GzDecoder;
-use git2::{
-    build::{CheckoutBuilder, RepoBuilder},
-    Repository,
-};
-use git2::{Cred, CredentialType, FetchOptions, RemoteCallbacks};
 use nix::sys::signal::Signal;
-use serde_derive::Deserialize;
-use serde_json::json;
 
-use crate::app::LogType;
+use crate::app::{LogLevel, LogSource};
 use crate::misc::{BodyString, RealIp};
+use crate::{check, git};
 use crate::{
     misc::{self, ValidateType},
-    project::{Project, ProjectStatus},
+    project::{DeployStrategy, Project, ProjectStatus},
     App,
 };
 
-#[derive(Deserialize)]
-struct RequestData {
-    // == Required ==
-    token: String,
-    name: String,
-    action: ActionType,
-
-    // == Stop action ==
-    signal: Option<String>,
-
-    // == Update action ==
-    /// Binary data (BASE64(GZIP(RAW)))
-    data: Option<String>,
-    /// Git origin (EX: origin)
-    remote: Option<String>,
-    /// Git branch to pull from
-    branch: Option<String>,
-    /// Commit / Tag to checkout
-    checkout: Option<String>,
-    /// Should merging be forced
-    force: Option<bool>,
-}
-
-#[derive(Deserialize)]
-enum ActionType {
-    Stop,
-    Start,
-    Update,
-    Reload,
-}
-
 pub fn attach(server: &mut Server, app: Arc<App>) {
     server.route(Method::POST, "/app/action", move |req| {
-        let body = serde_json::from_str::<RequestData>(&req.body_string()).unwrap();
+        let body = match serde_json::from_str::<AppActionRequest>(&req.body_string()) {
+            Ok(i) => i,
+            Err(e) => return misc::error_res(format!("Invalid request: {e}")),
+        };
+        let uploader = ValidateType::uploader(app.clone(), body.token.to_owned(), req.real_ip());
         if !ValidateType::Scoped(body.name.to_owned()).validate(app.clone(), body.token) {
             return misc::error_res("Invalid Token");
         }
 
-        let projects = app.projects.read();
-        let project = match projects.iter().find(|x| x.name == body.name) {
+        // Let a staged config be used for the new process
+        if body.action == ActionType::Start {
+            Project::apply_staged(app.clone());
+        }
+
+        // Not under the list lock, an update can build and restart for minutes
+        let project = match app.project(&body.name) {
             Some(i) => i,
             None => return misc::error_res("Invalid App"),
         };
@@ -77,154 +48,137 @@ pub fn attach(server: &mut Server, app: Arc<App>) {
                     return misc::error_res("App Already Stopped");
                 }
 
-                let mut sig = Signal::SIGINT;
-                if let Some(i) = body.signal {
-                    sig = Signal::from_str(&i).expect("Invalid signal type");
-                }
+                let sig = match body.signal.as_deref().map(Signal::from_str) {
+                    Some(Ok(i)) => i,
+                    Some(Err(_)) => return misc::error_res("Invalid signal type"),
+                    None => Signal::SIGINT,
+                };
 
-                project.stop(sig);
+                if let Err(e) = project.stop(sig) {
+                    return misc::error_res(e);
+                }
             }
             ActionType::Start => {
-                if *project.status.read() == ProjectStatus::Running {
+                if project.status.read().is_running() {
                     return misc::error_res("App Already Running");
                 }
-                project.start();
+                if let Err(e) = project.start() {
+                    return misc::error_res(e);
+                }
             }
             ActionType::Update => {
-                if *project.status.read() == ProjectStatus::Running {
+                // Taken first, so the app can not be started or stopped by another deploy after the check
+                let _deploying = project.deploy_lock.lock();
+
+                // Running apps can only be updated if they can be switched over without stopping
+                let running = project.status.read().is_running();
+                if running && project.config.deploy.strategy != DeployStrategy::BlueGreen {
                     return misc::error_res("App is still running");
                 }
+                let previous = project.current_release();
 
-                if let Some(i) = &project.config.git.repo {
-                    let branch = body.branch.expect("No Branch defined");
-                    let repo_path = project.project_path.join("repo");
-
-                    let mut checkout_bld = CheckoutBuilder::new();
-                    if body.force.unwrap_or(false) {
-                        checkout_bld.force();
+                // Checked before any hooks run
+                if project.config.git.repo.is_some() && body.branch.is_none() {
+                    return misc::error_res("No Branch defined");
+                }
+                let data = match body.data.map(decode_data).transpose() {
+                    Ok(i) => i,
+                    Err(e) => return misc::error_res(e),
+                };
+
+                let res = project.with_deploy_hooks(|| {
+                    if let (Some(i), Some(branch)) = (&project.config.git.repo, body.branch) {
+                        let update = git::Update {
+                            remote: body.remote.as_deref().unwrap_or("origin"),
+                            fetch: &branch,
+                            branch: &branch,
+                            checkout: body.checkout.as_deref(),
+                            force: body.force.unwrap_or(false),
+                        };
+
+                        let repo_path = project.project_path.join("repo");
+                        match git::update(&project.config.git, &repo_path, i, &update) {
+                            Ok(true) => {}
+                            Ok(false) => return Err("Merge conflicts o.o".to_owned()),
+                            Err(e) => return Err(format!("Error updating repo: {e}")),
+                        }
+
+                        project
+                            .build(&uploader)
+                            .map_err(|e| format!("Build failed: {e}"))?;
                     }
 
-                    if !repo_path.exists() {
-                        RepoBuilder::new()
-                            .with_checkout(checkout_bld)
-                            .fetch_options(git_auth_callback(project))
-                            .clone(i, &repo_path)
-                            .expect("Error cloning repo");
+                    if let Some(data) = data {
+                        project.add_release(&data, body.signature.as_deref(), None, &uploader)?;
                     }
 
-                    let repo = Repository::open(repo_path).expect("Error opening repo");
-                    let mut remote = repo
-                        .find_remote(body.remote.as_deref().unwrap_or("origin"))
-                        .expect("Remote not found");
-
-                    remote
-                        .fetch(&[&branch], Some(&mut git_auth_callback(project)), None)
-                        .unwrap();
-                    let fetch_head = match body.checkout {
-                        Some(i) => repo
-                            .resolve_reference_from_short_name(&i)
-                            .expect("Invalid reference"),
-                        None => repo.find_reference("FETCH_HEAD").unwrap(),
-                    };
-                    let fetch_commit = repo.reference_to_annotated_commit(&fetch_head).unwrap();
-                    if !misc::do_merge(&repo, &branch, fetch_commit).unwrap() {
-                        return misc::error_res("Merge conflicts o.o");
+                    if running {
+                        project
+                            .restart_or_revert(previous)
+                            .map_err(|e| format!("New instance failed: {e}"))?;
                     }
-                }
+                    Ok(())
+                });
 
-                if let Some(data) = body.data {
-                    let base64_dec = STANDARD.decode(data).expect("`data` is not valid base64");
-                    let mut gzip_dec = GzDecoder::new(Cursor::new(base64_dec));
-
-                    let mut out = Vec::new();
-                    gzip_dec.read_to_end(&mut out).expect("Error decompressing");
-                    fs::write(project.project_path.join("binary"), out)
-                        .expect("Error writing new binary");
+                if let Err(e) = res {
+                    return misc::error_res(e);
                 }
             }
             ActionType::Reload => {
-                if *project.status.read() == ProjectStatus::Running {
+                if project.status.read().is_running() {
                     return misc::error_res("App is still running");
                 }
                 let path = project.project_path.to_owned();
-                drop(projects);
 
+                let raw_config = match fs::read_to_string(path.join("config.toml")) {
+                    Ok(i) => i,
+                    Err(e) => return misc::error_res(format!("Error reading app config: {e}")),
+                };
+                if let Some(e) = check::check_project(&app, &raw_config, Some(&path))
+                    .into_iter()
+                    .find(|x| x.level == ProblemLevel::Error)
+                {
+                    return misc::error_res(format!("Invalid app config: {e}"));
+                }
+
+                let mut new = match Project::load_project(path, app.clone()) {
+                    Some(i) => i,
+                    None => return misc::error_res("Invalid app config"),
+                };
+                new.share_locks(&project);
                 let mut projects = app.projects.write();
                 projects.retain(|x| x.name != body.name);
-                projects.push(
-                    Project::load_project(path, app.clone())
-                        .expect("New project is invalid. Project unloaded."),
-                );
+                projects.push(Arc::new(new));
                 drop(projects);
             }
         }
 
-        app.log(
-            LogType::Info,
+        app.log_from(
+            LogSource::App(name.to_owned()),
+            LogLevel::Info,
             format!(
-                "[WEB] [{}] Triggered `{}` on `{}`",
+                "[{}] Triggered `{}` on `{}`",
                 req.real_ip(),
                 body.action,
                 name
             ),
         );
 
-        Response::new()
-            .text(json!({"status": "ok"}))
-            .content(Content::JSON)
-    });
-}
-
-fn git_auth_callback(project: &Project) -> FetchOptions {
-    let mut callbacks = RemoteCallbacks::new();
-    callbacks.credentials(|_url, username_from_url, allowed_types| {
-        let username = project
-            .config
-            .git
-            .username
-            .as_ref()
-            .expect("No project git username defined");
-
-        let token = project.config.git.token.as_ref();
-        let ssh_key = project.config.git.ssh_key_file.as_ref();
-
-        if allowed_types.contains(CredentialType::SSH_MEMORY) {
-            if let Some(i) = ssh_key {
-                return Cred::ssh_key(
-                    username_from_url.unwrap_or(username),
-                    None,
-                    Path::new(i),
-                    None,
-                );
-            }
-
-            panic!("Tried to use ssh auth, no private key defined");
-        }
-
-        if allowed_types.contains(CredentialType::USER_PASS_PLAINTEXT) {
-            if let Some(i) = token {
-                return Cred::userpass_plaintext(username_from_url.unwrap_or(username), i);
-            }
-
-            panic!("Tried to use token auth, no token defined");
-        }
-
-        panic!("No valid git auth found")
+        misc::json_res(&ActionResponse {
+            status: "ok".to_owned(),
+        })
     });
-
-    let mut fo = FetchOptions::new();
-    fo.remote_callbacks(callbacks);
-    fo.download_tags(git2::AutotagOption::All);
-    fo
 }
 
-impl Display for ActionType {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(match self {
-            Self::Reload => "reload",
-            Self::Start => "start",
-            Self::Stop => "stop",
-            Self::Update => "update",
-        })
-    }
+/// Decodes the `BASE64(GZIP(RAW))` binary of an `Update`
+fn decode_data(data: String) -> Result<Vec<u8>, String> {
+    let gzip = STANDARD
+        .decode(data)
+        .map_err(|e| format!("`data` is not valid base64: {e}"))?;
+
+    let mut out = Vec::new();
+    GzDecoder::new(Cursor::new(gzip))
+        .read_to_end(&mut out)
+        .map_err(|e| format!("Error decompressing `data`: {e}"))?;
+    Ok(out)
 }
@@ -0,0 +1,46 @@
+use std::sync::Arc;
+
+use afire::{Method, Server};
+use chalk_client::models::{AppControlRequest, AppControlResponse};
+
+use crate::{
+    app::{LogLevel, LogSource},
+    misc::{self, BodyString, RealIp, ValidateType},
+    App,
+};
+
+pub fn attach(server: &mut Server, app: Arc<App>) {
+    server.route(Method::POST, "/app/control", move |req| {
+        let body = match serde_json::from_str::<AppControlRequest>(&req.body_string()) {
+            Ok(i) => i,
+            Err(e) => return misc::error_res(format!("Invalid request: {e}")),
+        };
+        if !ValidateType::Scoped(body.name.to_owned()).validate(app.clone(), body.token) {
+            return misc::error_res("Invalid Token");
+        }
+
+        // Not under the list lock, the app can take up to `control.timeout` to answer
+        let project = match app.project(&body.name) {
+            Some(i) => i,
+            None => return misc::error_res("Invalid App"),
+        };
+
+        let (status, res) = match project.control(&body.action, body.data) {
+            Ok(i) => i,
+            Err(e) => return misc::error_res(e),
+        };
+
+        app.log_from(
+            LogSource::App(body.name.to_owned()),
+            LogLevel::Info,
+            format!(
+                "[{}] Ran control action `{}` on `{}` ({status})",
+                req.real_ip(),
+                body.action,
+                body.name
+            ),
+        );
+
+        misc::json_res(&AppControlResponse { status, body: res })
+    });
+}
@@ -0,0 +1,67 @@
+use std::fs;
+use std::sync::Arc;
+
+use afire::{Method, Server};
+use chalk_client::models::{AppCreateRequest, AppCreateResponse, ProblemLevel};
+
+use crate::{
+    app::{LogLevel, LogSource},
+    check,
+    misc::{self, BodyString, RealIp, ValidateType},
+    project::{Project, ProjectConfig},
+    App,
+};
+
+pub fn attach(server: &mut Server, app: Arc<App>) {
+    server.route(Method::POST, "/app/create", move |req| {
+        let body = serde_json::from_str::<AppCreateRequest>(&req.body_string()).unwrap();
+        if !ValidateType::Global.validate(app.clone(), body.token) {
+            return misc::error_res("Invalid Token");
+        }
+
+        // Same checks as `chalk config check`, before anything is written
+        if let Some(e) = check::check_project(&app, &body.config, None)
+            .into_iter()
+            .find(|x| x.level == ProblemLevel::Error)
+        {
+            return misc::error_res(format!("Invalid app config: {e}"));
+        }
+
+        let config = match toml::from_str::<ProjectConfig>(&body.config) {
+            Ok(i) => i,
+            Err(e) => return misc::error_res(format!("Invalid app config: {e}")),
+        };
+
+        // Checked again with the list locked, another app could have been made since
+        let mut projects = app.projects.write();
+        if let Err(e) = Project::check_unique(&config, projects.iter(), &app) {
+            return misc::error_res(e);
+        }
+
+        let path = app.apps_path().join(&config.name);
+        if path.exists() {
+            return misc::error_res("App folder already exists");
+        }
+
+        if let Err(e) = fs::create_dir_all(&path)
+            .and_then(|_| fs::write(path.join("config.toml"), &body.config))
+        {
+            return misc::error_res(format!("Error writing app config: {e}"));
+        }
+
+        let project = match Project::load_project(path, app.clone()) {
+            Some(i) => i,
+            None => return misc::error_res("Error loading app"),
+        };
+        let name = project.name.to_owned();
+        projects.push(Arc::new(project));
+
+        app.log_from(
+            LogSource::App(name.to_owned()),
+            LogLevel::Info,
+            format!("[{}] Created app `{}`", req.real_ip(), name),
+        );
+
+        misc::json_res(&AppCreateResponse { name })
+    });
+}
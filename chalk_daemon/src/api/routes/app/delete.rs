@@ -0,0 +1,64 @@
+use std::fs;
+use std::sync::Arc;
+
+use afire::{Method, Server};
+use chalk_client::models::{AppDeleteRequest, AppDeleteResponse};
+use chrono::Utc;
+use nix::sys::signal::Signal;
+
+use crate::{
+    app::{LogLevel, LogSource},
+    misc::{self, BodyString, RealIp, ValidateType},
+    project::STOP_TIMEOUT,
+    App,
+};
+
+pub fn attach(server: &mut Server, app: Arc<App>) {
+    server.route(Method::POST, "/app/delete", move |req| {
+        let body = serde_json::from_str::<AppDeleteRequest>(&req.body_string()).unwrap();
+        if !ValidateType::Global.validate(app.clone(), body.token) {
+            return misc::error_res("Invalid Token");
+        }
+
+        let project = match app.project(&body.name) {
+            Some(i) => i,
+            None => return misc::error_res("Invalid App"),
+        };
+        let _deploying = project.deploy_lock.lock();
+
+        if let Err(e) = project.stop_wait(Signal::SIGINT, STOP_TIMEOUT) {
+            return misc::error_res(e);
+        }
+        let path = project.project_path.to_owned();
+
+        // The app stays loaded if its folder can not be moved or removed
+        let archive = if body.archive.unwrap_or(false) {
+            let archive = app.app_dir.preference_dir().join("archive").join(format!(
+                "{}-{}",
+                body.name,
+                Utc::now().format("%Y-%m-%d_%H-%M-%S")
+            ));
+
+            if let Err(e) = fs::create_dir_all(archive.parent().unwrap())
+                .and_then(|_| fs::rename(&path, &archive))
+            {
+                return misc::error_res(format!("Error archiving app: {e}"));
+            }
+            Some(archive.to_string_lossy().into_owned())
+        } else {
+            if let Err(e) = fs::remove_dir_all(&path) {
+                return misc::error_res(format!("Error removing app: {e}"));
+            }
+            None
+        };
+        app.projects.write().retain(|x| x.name != body.name);
+
+        app.log_from(
+            LogSource::App(body.name.to_owned()),
+            LogLevel::Info,
+            format!("[{}] Deleted app `{}`", req.real_ip(), body.name),
+        );
+
+        misc::json_res(&AppDeleteResponse { archive })
+    });
+}
@@ -1,23 +1,21 @@
 use std::sync::{atomic::Ordering, Arc};
 
-use afire::{Content, Method, Response, Server};
-use serde_derive::Deserialize;
-use serde_json::{json, Value};
+use afire::{Method, Server};
+use chalk_client::models::{AppInfoRequest, AppInfoResponse, Output, ProcessInfo};
 
 use crate::{
+    git,
     misc::{self, BodyString, ValidateType},
-    App, Project,
+    project::Process,
+    App,
 };
 
-#[derive(Deserialize)]
-struct RequestData {
-    token: String,
-    name: String,
-}
-
 pub fn attach(server: &mut Server, app: Arc<App>) {
     server.route(Method::GET, "/app/info", move |req| {
-        let body = serde_json::from_str::<RequestData>(&req.body_string()).unwrap();
+        let body = match serde_json::from_str::<AppInfoRequest>(&req.body_string()) {
+            Ok(i) => i,
+            Err(e) => return misc::error_res(format!("Invalid request: {e}")),
+        };
         if !ValidateType::Scoped(body.name.to_owned()).validate(app.clone(), body.token) {
             return misc::error_res("Invalid Token");
         }
@@ -29,27 +27,26 @@ pub fn attach(server: &mut Server, app: Arc<App>) {
         };
 
         // Get std(out|err)
-        let stdout = app.process.stdout.read();
-        let stdout = String::from_utf8_lossy(&stdout);
-        let stderr = app.process.stderr.read();
-        let stderr = String::from_utf8_lossy(&stderr);
-
-        Response::new()
-            .text(json!({
-                "name": app.name,
-                "status": *app.status.read(),
-                "output": {
-                    "stdout": stdout,
-                    "stderr": stderr,
-                },
-                "info": get_info(app)
-            }))
-            .content(Content::JSON)
+        let stdout = String::from_utf8_lossy(&app.process.stdout.read()).into_owned();
+        let stderr = String::from_utf8_lossy(&app.process.stderr.read()).into_owned();
+
+        let status = *app.status.read();
+        let next = app.next.lock().as_ref().and_then(get_info);
+        let report = app.process.report.read().clone();
+        misc::json_res(&AppInfoResponse {
+            name: app.name.to_owned(),
+            status,
+            output: Output { stdout, stderr },
+            info: get_info(&app.process),
+            next,
+            revision: git::revision(&app.project_path.join("repo")),
+            report,
+        })
     });
 }
 
-fn get_info(app: &Project) -> Option<Value> {
-    let i = app.process.process.lock();
+fn get_info(process: &Process) -> Option<ProcessInfo> {
+    let i = process.process.lock();
     let i = i.as_ref()?;
 
     // Get info on the process
@@ -57,10 +54,10 @@ fn get_info(app: &Project) -> Option<Value> {
     let mem_info = procinfo::pid::statm(pid).ok()?;
     let stats = procinfo::pid::stat(pid).ok()?;
 
-    Some(json!({
-        "pid": pid,
-        "memory": mem_info.size,
-        "threads": stats.num_threads,
-        "uptime": app.process.uptime.load(Ordering::Relaxed)
-    }))
+    Some(ProcessInfo {
+        pid,
+        memory: mem_info.size,
+        threads: stats.num_threads,
+        uptime: process.uptime.load(Ordering::Relaxed),
+    })
 }
@@ -0,0 +1,179 @@
+use std::sync::Arc;
+
+use afire::{Method, Server};
+use chalk_client::models::{AppLogsRequest, AppLogsResponse, LogLevel, LogRecord};
+use serde_json::Value;
+
+use crate::{
+    misc::{self, BodyString, ValidateType},
+    App,
+};
+
+pub fn attach(server: &mut Server, app: Arc<App>) {
+    server.route(Method::POST, "/app/logs", move |req| {
+        let body = match serde_json::from_str::<AppLogsRequest>(&req.body_string()) {
+            Ok(i) => i,
+            Err(e) => return misc::error_res(format!("Invalid request: {e}")),
+        };
+        if !ValidateType::Scoped(body.name.to_owned()).validate(app.clone(), body.token) {
+            return misc::error_res("Invalid Token");
+        }
+
+        let projects = app.projects.read();
+        let project = match projects.iter().find(|x| x.name == body.name) {
+            Some(i) => i,
+            None => return misc::error_res("Invalid App"),
+        };
+
+        let fields = body.fields.iter().collect::<Vec<_>>();
+        let records = project.process.records.read();
+        let filterd = records
+            .iter()
+            .filter(|x| matches(x, body.level, body.target.as_deref(), &fields))
+            .collect::<Vec<_>>();
+
+        let (logs, end) = page(&filterd, body.page, body.lines);
+        misc::json_res(&AppLogsResponse { logs, end })
+    });
+}
+
+/// Gets a page of records, newest first, and if it is the last page
+fn page(records: &[&LogRecord], page: usize, lines: usize) -> (Vec<LogRecord>, bool) {
+    let end = records.len() <= (page + 1) * lines;
+    let logs = records
+        .iter()
+        .rev()
+        .skip(page * lines)
+        .take(lines)
+        .map(|x| (*x).clone())
+        .collect();
+
+    (logs, end)
+}
+
+/// Checks if a record matches the filters of an app logs request
+fn matches(
+    record: &LogRecord,
+    level: Option<LogLevel>,
+    target: Option<&str>,
+    fields: &[(&String, &String)],
+) -> bool {
+    if level.map(|x| record.level < x).unwrap_or(false) {
+        return false;
+    }
+
+    if let Some(target) = target {
+        match &record.target {
+            Some(i) if i.starts_with(target) => {}
+            _ => return false,
+        }
+    }
+
+    fields
+        .iter()
+        .all(|(key, value)| match record.fields.get(*key) {
+            Some(Value::String(i)) => i == *value,
+            Some(i) => serde_json::from_str::<Value>(value).ok().as_ref() == Some(i),
+            None => false,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde_json::json;
+
+    use super::*;
+
+    fn record(message: &str, level: LogLevel, target: Option<&str>, fields: Value) -> LogRecord {
+        LogRecord {
+            time: 0,
+            level,
+            message: message.to_owned(),
+            target: target.map(str::to_owned),
+            fields: serde_json::from_value::<BTreeMap<_, _>>(fields).unwrap(),
+        }
+    }
+
+    fn filter(record: &LogRecord, fields: &[(&str, &str)]) -> bool {
+        let fields = fields
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect::<Vec<_>>();
+        let fields = fields.iter().map(|(k, v)| (k, v)).collect::<Vec<_>>();
+        matches(record, None, None, &fields)
+    }
+
+    #[test]
+    fn filters_level() {
+        let record = record("x", LogLevel::Warn, None, json!({}));
+        assert!(matches(&record, None, None, &[]));
+        assert!(matches(&record, Some(LogLevel::Info), None, &[]));
+        assert!(matches(&record, Some(LogLevel::Warn), None, &[]));
+        assert!(!matches(&record, Some(LogLevel::Error), None, &[]));
+    }
+
+    #[test]
+    fn filters_target_prefix() {
+        let record = record("x", LogLevel::Info, Some("app::db::pool"), json!({}));
+        assert!(matches(&record, None, Some("app::db"), &[]));
+        assert!(matches(&record, None, Some("app::db::pool"), &[]));
+        assert!(!matches(&record, None, Some("app::http"), &[]));
+
+        let untargeted = self::record("x", LogLevel::Info, None, json!({}));
+        assert!(!matches(&untargeted, None, Some("app"), &[]));
+    }
+
+    #[test]
+    fn filters_fields() {
+        let record = record(
+            "x",
+            LogLevel::Info,
+            None,
+            json!({ "user": "connor", "status": 404, "cached": false }),
+        );
+
+        assert!(filter(&record, &[("user", "connor")]));
+        assert!(!filter(&record, &[("user", "other")]));
+        assert!(!filter(&record, &[("missing", "connor")]));
+
+        // Non string values are compared as JSON
+        assert!(filter(&record, &[("status", "404")]));
+        assert!(filter(&record, &[("cached", "false")]));
+        assert!(!filter(&record, &[("status", "\"404\"")]));
+        assert!(!filter(&record, &[("status", "40")]));
+
+        // Every field has to match
+        assert!(filter(&record, &[("user", "connor"), ("status", "404")]));
+        assert!(!filter(&record, &[("user", "connor"), ("status", "500")]));
+    }
+
+    #[test]
+    fn pages_newest_first() {
+        let records = (0..5)
+            .map(|x| record(&x.to_string(), LogLevel::Info, None, json!({})))
+            .collect::<Vec<_>>();
+        let records = records.iter().collect::<Vec<_>>();
+        let messages = |page: usize, lines: usize| {
+            let (logs, end) = self::page(&records, page, lines);
+            let logs = logs.into_iter().map(|x| x.message).collect::<Vec<_>>();
+            (logs, end)
+        };
+
+        assert_eq!(
+            messages(0, 2),
+            (vec!["4".to_owned(), "3".to_owned()], false)
+        );
+        assert_eq!(
+            messages(1, 2),
+            (vec!["2".to_owned(), "1".to_owned()], false)
+        );
+        assert_eq!(messages(2, 2), (vec!["0".to_owned()], true));
+        assert_eq!(messages(3, 2), (vec![], true));
+
+        // A page that ends right at the last record is the end
+        assert!(messages(0, 5).1);
+        assert!(!messages(0, 4).1);
+    }
+}
@@ -5,9 +5,25 @@ use afire::Server;
 use crate::App;
 
 mod action;
+mod control;
+mod create;
+mod delete;
 mod info;
+mod logs;
+mod releases;
+mod report;
+mod rollback;
+mod webhook;
 
 pub fn attach(server: &mut Server, app: Arc<App>) {
     action::attach(server, app.clone());
-    info::attach(server, app);
+    control::attach(server, app.clone());
+    create::attach(server, app.clone());
+    delete::attach(server, app.clone());
+    info::attach(server, app.clone());
+    logs::attach(server, app.clone());
+    releases::attach(server, app.clone());
+    report::attach(server, app.clone());
+    rollback::attach(server, app.clone());
+    webhook::attach(server, app);
 }
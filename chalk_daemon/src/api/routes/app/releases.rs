@@ -0,0 +1,29 @@
+use std::sync::Arc;
+
+use afire::{Method, Server};
+use chalk_client::models::{AppReleasesRequest, AppReleasesResponse};
+
+use crate::{
+    misc::{self, BodyString, ValidateType},
+    App,
+};
+
+pub fn attach(server: &mut Server, app: Arc<App>) {
+    server.route(Method::POST, "/app/releases", move |req| {
+        let body = serde_json::from_str::<AppReleasesRequest>(&req.body_string()).unwrap();
+        if !ValidateType::Scoped(body.name.to_owned()).validate(app.clone(), body.token) {
+            return misc::error_res("Invalid Token");
+        }
+
+        let projects = app.projects.read();
+        let project = match projects.iter().find(|x| x.name == body.name) {
+            Some(i) => i,
+            None => return misc::error_res("Invalid App"),
+        };
+
+        misc::json_res(&AppReleasesResponse {
+            current: project.current_release(),
+            releases: project.releases(),
+        })
+    });
+}
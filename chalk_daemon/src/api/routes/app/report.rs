@@ -0,0 +1,35 @@
+use std::sync::Arc;
+
+use afire::{Method, Server};
+use chalk_client::models::{ActionResponse, AppReportRequest};
+
+use crate::{
+    misc::{self, header},
+    App,
+};
+
+pub fn attach(server: &mut Server, app: Arc<App>) {
+    // Called by apps, signed with their interface secret instead of a token
+    server.route(Method::POST, "/app/report/{name}", move |req| {
+        let name = req.param("name").unwrap();
+        let project = match app.project(&name) {
+            Some(i) => i,
+            None => return misc::error_res("Invalid App"),
+        };
+
+        let next = match project.verify_report(|x| header(req, x), &req.body) {
+            Ok(i) => i,
+            Err(e) => return misc::error_res(e),
+        };
+
+        let report = match serde_json::from_slice::<AppReportRequest>(&req.body) {
+            Ok(i) => i,
+            Err(e) => return misc::error_res(format!("Invalid report: {e}")),
+        };
+        project.apply_report(report, next);
+
+        misc::json_res(&ActionResponse {
+            status: "ok".to_owned(),
+        })
+    });
+}
@@ -0,0 +1,57 @@
+use std::sync::Arc;
+
+use afire::{Method, Server};
+use chalk_client::models::{AppRollbackRequest, AppRollbackResponse};
+
+use crate::{
+    app::{LogLevel, LogSource},
+    misc::{self, BodyString, RealIp, ValidateType},
+    App,
+};
+
+pub fn attach(server: &mut Server, app: Arc<App>) {
+    server.route(Method::POST, "/app/rollback", move |req| {
+        let body = serde_json::from_str::<AppRollbackRequest>(&req.body_string()).unwrap();
+        if !ValidateType::Scoped(body.name.to_owned()).validate(app.clone(), body.token) {
+            return misc::error_res("Invalid Token");
+        }
+
+        let project = match app.project(&body.name) {
+            Some(i) => i,
+            None => return misc::error_res("Invalid App"),
+        };
+        let _deploying = project.deploy_lock.lock();
+
+        let release = match body.release.or_else(|| project.previous_release()) {
+            Some(i) => i,
+            None => return misc::error_res("No older release"),
+        };
+
+        let previous = project.current_release();
+        let res = project.with_deploy_hooks(|| {
+            project.switch_release(release)?;
+            if project.status.read().is_running() {
+                project
+                    .restart_or_revert(previous)
+                    .map_err(|e| format!("New instance failed: {e}"))?;
+            }
+            Ok(())
+        });
+
+        if let Err(e) = res {
+            return misc::error_res(e);
+        }
+
+        app.log_from(
+            LogSource::App(body.name.to_owned()),
+            LogLevel::Info,
+            format!(
+                "[{}] Rolled `{}` back to release {release}",
+                req.real_ip(),
+                body.name
+            ),
+        );
+
+        misc::json_res(&AppRollbackResponse { release })
+    });
+}
@@ -0,0 +1,451 @@
+use std::sync::Arc;
+use std::thread;
+
+use afire::{Method, Server};
+use chalk_client::models::ActionResponse;
+use hmac::{Hmac, Mac};
+use nix::sys::signal::Signal;
+use serde_derive::Deserialize;
+use sha2::Sha256;
+
+use crate::{
+    app::{LogLevel, LogSource},
+    git,
+    misc::{self, header, RealIp},
+    project::{DeployStrategy, Hook, Project, ProjectWebhookConfig, STOP_TIMEOUT},
+    App,
+};
+
+#[derive(Deserialize)]
+struct PushEvent {
+    #[serde(rename = "ref")]
+    git_ref: String,
+    /// Commit after the push, all zeros if the ref was deleted
+    after: String,
+}
+
+#[derive(Clone, Copy)]
+enum Provider {
+    GitHub,
+    GitLab,
+    Gitea,
+}
+
+/// What to fetch and where to merge it
+struct Target {
+    fetch: String,
+    branch: String,
+    provider: Provider,
+}
+
+pub fn attach(server: &mut Server, app: Arc<App>) {
+    server.route(Method::POST, "/app/webhook/{name}", move |req| {
+        let name = req.param("name").unwrap();
+        let project = match app.project(&name) {
+            Some(i) => i,
+            None => return misc::error_res("Invalid App"),
+        };
+        let webhook = match &project.config.webhook {
+            Some(i) => i,
+            None => return misc::error_res("Invalid App"),
+        };
+
+        // Anyone could send a GitLab webhook with an empty token
+        if webhook.secret.is_empty() {
+            return misc::error_res("Webhook secret is not set");
+        }
+
+        let header = |x: &str| header(req, x);
+        let provider = match Provider::detect(header) {
+            Some(i) => i,
+            None => return misc::error_res("Unknown webhook provider"),
+        };
+
+        if !provider.verify(header, &req.body, &webhook.secret) {
+            app.log_from(
+                LogSource::App(name.to_owned()),
+                LogLevel::Warn,
+                format!("[{}] Invalid webhook signature for `{name}`", req.real_ip()),
+            );
+            return misc::error_res("Invalid Signature");
+        }
+
+        if !provider.is_push(header) {
+            return ignored();
+        }
+
+        let event = match serde_json::from_slice::<PushEvent>(&req.body) {
+            Ok(i) => i,
+            Err(_) => return misc::error_res("Invalid Payload"),
+        };
+
+        let target = match Target::from_push(webhook, &event, provider) {
+            Some(i) => i,
+            None => return ignored(),
+        };
+
+        app.log_from(
+            LogSource::App(name.to_owned()),
+            LogLevel::Info,
+            format!(
+                "[{}] Webhook push of `{}` to `{name}`",
+                req.real_ip(),
+                event.git_ref
+            ),
+        );
+
+        let app = app.clone();
+        thread::Builder::new()
+            .name("Webhook".into())
+            .spawn(move || deploy(app, project, target))
+            .unwrap();
+
+        misc::json_res(&ActionResponse {
+            status: "queued".to_owned(),
+        })
+        .status(202)
+    });
+}
+
+/// Stops the app, pulls the push, builds it and starts it again.
+/// Blue/green apps keep running until the new build is ready.
+fn deploy(app: Arc<App>, project: Arc<Project>, target: Target) {
+    // Waits for any other deploy of the app to finish
+    let _deploying = project.deploy_lock.lock();
+    let name = project.name.to_owned();
+    let (repo, webhook) = match (&project.config.git.repo, &project.config.webhook) {
+        (Some(repo), Some(webhook)) => (repo, webhook),
+        _ => {
+            project.log(
+                LogLevel::Error,
+                format!("Webhook for `{name}` needs `git.repo` to be set"),
+            );
+            return;
+        }
+    };
+
+    let was_running = project.status.read().is_running();
+    let previous = project.current_release();
+    let blue_green = was_running && project.config.deploy.strategy == DeployStrategy::BlueGreen;
+    // Hook failures are logged by the hooks
+    if project.run_hook(Hook::PreDeploy).is_err() {
+        return;
+    }
+    if !blue_green && project.stop_wait(Signal::SIGINT, STOP_TIMEOUT).is_err() {
+        return;
+    }
+
+    let update = git::Update {
+        remote: &webhook.remote,
+        fetch: &target.fetch,
+        branch: &target.branch,
+        checkout: None,
+        force: false,
+    };
+    let repo_path = project.project_path.join("repo");
+    let updated = match git::update(&project.config.git, &repo_path, repo, &update) {
+        Ok(true) => project
+            .build(&format!("{} webhook", target.provider.name()))
+            .is_ok(),
+        Ok(false) => {
+            project.log(
+                LogLevel::Error,
+                format!("Merge conflicts updating `{name}` to `{}`", target.fetch),
+            );
+            false
+        }
+        Err(e) => {
+            project.log(
+                LogLevel::Error,
+                format!("Error updating `{name}` to `{}`: {e}", target.fetch),
+            );
+            false
+        }
+    };
+
+    // The old instance is still running, so it only needs to be switched over
+    if blue_green {
+        if !updated {
+            return;
+        }
+
+        if project.restart_or_revert(previous).is_err() {
+            return;
+        }
+
+        project.spawn_hook(Hook::PostDeploy);
+        project.log(
+            LogLevel::Info,
+            format!("Deployed `{}` to `{name}`", target.fetch),
+        );
+        return;
+    }
+
+    // Only apps that were running before are started again
+    if !was_running {
+        if updated {
+            project.spawn_hook(Hook::PostDeploy);
+            project.log(
+                LogLevel::Info,
+                format!("Deployed `{}` to `{name}`", target.fetch),
+            );
+        }
+        return;
+    }
+
+    // A config staged during the deploy replaces the project, so it is looked up again.
+    // Errors are logged by `start`
+    Project::apply_staged(app.clone());
+    let project = match app.project(&name) {
+        Some(i) => i,
+        None => return,
+    };
+
+    // Bring the old version back up if the update failed
+    if !updated {
+        let _ = project.start();
+        return;
+    }
+
+    // Same as the `Update` action, a release that does not start is switched back from
+    if project.restart_or_revert(previous).is_err() {
+        if previous.is_some() {
+            let _ = project.start();
+        }
+        return;
+    }
+
+    project.spawn_hook(Hook::PostDeploy);
+    project.log(
+        LogLevel::Info,
+        format!("Deployed `{}` to `{name}`", target.fetch),
+    );
+}
+
+impl Target {
+    /// What to deploy for a push, None if the branch or tag is not deployed
+    fn from_push(
+        webhook: &ProjectWebhookConfig,
+        event: &PushEvent,
+        provider: Provider,
+    ) -> Option<Self> {
+        // Deleted branches and tags
+        if event.after.chars().all(|x| x == '0') {
+            return None;
+        }
+
+        if let Some(branch) = event.git_ref.strip_prefix("refs/heads/") {
+            let pattern = webhook.branch.as_ref()?;
+            return misc::glob_match(pattern, branch).then(|| Self {
+                fetch: branch.to_owned(),
+                branch: branch.to_owned(),
+                provider,
+            });
+        }
+
+        if let Some(tag) = event.git_ref.strip_prefix("refs/tags/") {
+            let pattern = webhook.tag.as_ref()?;
+            return misc::glob_match(pattern, tag).then(|| Self {
+                fetch: event.git_ref.to_owned(),
+                branch: tag.to_owned(),
+                provider,
+            });
+        }
+
+        None
+    }
+}
+
+impl Provider {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::GitHub => "GitHub",
+            Self::GitLab => "GitLab",
+            Self::Gitea => "Gitea",
+        }
+    }
+
+    fn detect<'a>(header: impl Fn(&str) -> Option<&'a str>) -> Option<Self> {
+        // Gitea also sends the GitHub headers, so it is checked first
+        if header("X-Gitea-Event").is_some() {
+            return Some(Self::Gitea);
+        }
+
+        if header("X-GitHub-Event").is_some() {
+            return Some(Self::GitHub);
+        }
+
+        if header("X-Gitlab-Event").is_some() {
+            return Some(Self::GitLab);
+        }
+
+        None
+    }
+
+    /// GitHub and Gitea sign the body with HMAC-SHA256.
+    /// GitLab only sends the secret back in a header.
+    fn verify<'a>(
+        &self,
+        header: impl Fn(&str) -> Option<&'a str>,
+        body: &[u8],
+        secret: &str,
+    ) -> bool {
+        let signature = match self {
+            Self::GitHub => header("X-Hub-Signature-256").and_then(|x| x.strip_prefix("sha256=")),
+            Self::Gitea => header("X-Gitea-Signature"),
+            Self::GitLab => {
+                return header("X-Gitlab-Token")
+                    .map(|x| misc::constant_time_eq(x.as_bytes(), secret.as_bytes()))
+                    .unwrap_or(false)
+            }
+        };
+
+        let signature = match signature.and_then(|x| hex::decode(x).ok()) {
+            Some(i) => i,
+            None => return false,
+        };
+
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(body);
+        mac.verify_slice(&signature).is_ok()
+    }
+
+    fn is_push<'a>(&self, header: impl Fn(&str) -> Option<&'a str>) -> bool {
+        match self {
+            Self::GitHub => header("X-GitHub-Event") == Some("push"),
+            Self::Gitea => header("X-Gitea-Event") == Some("push"),
+            Self::GitLab => matches!(
+                header("X-Gitlab-Event"),
+                Some("Push Hook" | "Tag Push Hook")
+            ),
+        }
+    }
+}
+
+fn ignored() -> afire::Response {
+    misc::json_res(&ActionResponse {
+        status: "ignored".to_owned(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "chalk-test-secret";
+    const GITHUB_PUSH: &[u8] = include_bytes!("../../../../tests/webhooks/github_push.json");
+    const GITHUB_DELETE: &[u8] = include_bytes!("../../../../tests/webhooks/github_delete.json");
+    const GITLAB_TAG_PUSH: &[u8] =
+        include_bytes!("../../../../tests/webhooks/gitlab_tag_push.json");
+    /// `X-Hub-Signature-256` of `GITHUB_PUSH` signed with `SECRET`
+    const GITHUB_SIGNATURE: &str =
+        "sha256=5bf1beedf937bc3b3ec12790af2b057c87c1abfde93ad7e9f032be0e180fc08c";
+
+    fn headers<'a>(list: &'a [(&str, &'a str)]) -> impl Fn(&str) -> Option<&'a str> + 'a {
+        move |name| {
+            list.iter()
+                .find(|x| x.0.eq_ignore_ascii_case(name))
+                .map(|x| x.1)
+        }
+    }
+
+    fn webhook(branch: Option<&str>, tag: Option<&str>) -> ProjectWebhookConfig {
+        ProjectWebhookConfig {
+            secret: SECRET.to_owned(),
+            remote: "origin".to_owned(),
+            branch: branch.map(str::to_owned),
+            tag: tag.map(str::to_owned),
+        }
+    }
+
+    fn event(payload: &[u8]) -> PushEvent {
+        serde_json::from_slice(payload).unwrap()
+    }
+
+    #[test]
+    fn github_signature() {
+        let list = [
+            ("X-GitHub-Event", "push"),
+            ("X-Hub-Signature-256", GITHUB_SIGNATURE),
+        ];
+        let header = headers(&list);
+        let provider = Provider::detect(&header).unwrap();
+        assert!(matches!(provider, Provider::GitHub));
+        assert!(provider.is_push(&header));
+
+        assert!(provider.verify(&header, GITHUB_PUSH, SECRET));
+        assert!(!provider.verify(&header, GITHUB_PUSH, "wrong-secret"));
+
+        let mut tampered = GITHUB_PUSH.to_vec();
+        tampered[10] ^= 1;
+        assert!(!provider.verify(&header, &tampered, SECRET));
+
+        let unsigned = [("X-GitHub-Event", "push")];
+        assert!(!provider.verify(headers(&unsigned), GITHUB_PUSH, SECRET));
+    }
+
+    #[test]
+    fn gitlab_token() {
+        let list = [
+            ("X-Gitlab-Event", "Tag Push Hook"),
+            ("X-Gitlab-Token", SECRET),
+        ];
+        let header = headers(&list);
+        let provider = Provider::detect(&header).unwrap();
+        assert!(matches!(provider, Provider::GitLab));
+        assert!(provider.is_push(&header));
+        assert!(provider.verify(&header, GITLAB_TAG_PUSH, SECRET));
+        assert!(!provider.verify(&header, GITLAB_TAG_PUSH, "wrong-secret"));
+
+        let wrong = [("X-Gitlab-Event", "Push Hook"), ("X-Gitlab-Token", "guess")];
+        assert!(!provider.verify(headers(&wrong), GITLAB_TAG_PUSH, SECRET));
+
+        let missing = [("X-Gitlab-Event", "Push Hook")];
+        assert!(!provider.verify(headers(&missing), GITLAB_TAG_PUSH, SECRET));
+    }
+
+    #[test]
+    fn gitea_before_github() {
+        let list = [("X-GitHub-Event", "push"), ("X-Gitea-Event", "push")];
+        assert!(matches!(
+            Provider::detect(headers(&list)),
+            Some(Provider::Gitea)
+        ));
+        assert!(Provider::detect(headers(&[])).is_none());
+
+        let list = [("X-GitHub-Event", "ping")];
+        assert!(!Provider::GitHub.is_push(headers(&list)));
+    }
+
+    #[test]
+    fn branch_filter() {
+        let push = event(GITHUB_PUSH);
+        let target =
+            Target::from_push(&webhook(Some("release/*"), None), &push, Provider::GitHub).unwrap();
+        assert_eq!(target.fetch, "release/1.2");
+        assert_eq!(target.branch, "release/1.2");
+
+        assert!(Target::from_push(&webhook(Some("main"), None), &push, Provider::GitHub).is_none());
+        assert!(Target::from_push(&webhook(None, Some("*")), &push, Provider::GitHub).is_none());
+    }
+
+    #[test]
+    fn tag_filter() {
+        let push = event(GITLAB_TAG_PUSH);
+        let target =
+            Target::from_push(&webhook(None, Some("v1.*")), &push, Provider::GitLab).unwrap();
+        assert_eq!(target.fetch, "refs/tags/v1.2.0");
+        assert_eq!(target.branch, "v1.2.0");
+
+        assert!(Target::from_push(&webhook(None, Some("v2.*")), &push, Provider::GitLab).is_none());
+        assert!(Target::from_push(&webhook(Some("*"), None), &push, Provider::GitLab).is_none());
+    }
+
+    #[test]
+    fn deleted_refs_ignored() {
+        let push = event(GITHUB_DELETE);
+        assert!(
+            Target::from_push(&webhook(Some("*"), Some("*")), &push, Provider::GitHub).is_none()
+        );
+    }
+}
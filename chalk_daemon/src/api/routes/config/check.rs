@@ -0,0 +1,51 @@
+use std::sync::Arc;
+
+use afire::{Method, Server};
+use chalk_client::models::{ConfigCheckRequest, ConfigCheckResponse, ConfigKind, ConfigReport};
+
+use crate::{
+    check,
+    misc::{self, BodyString, ValidateType},
+    App,
+};
+
+pub fn attach(server: &mut Server, app: Arc<App>) {
+    server.route(Method::POST, "/config/check", move |req| {
+        let body = serde_json::from_str::<ConfigCheckRequest>(&req.body_string()).unwrap();
+        if !ValidateType::Global.validate(app.clone(), body.token) {
+            return misc::error_res("Invalid Token");
+        }
+
+        // Dry run everything on disk
+        let config = match body.config {
+            Some(i) => i,
+            None => {
+                return misc::json_res(&ConfigCheckResponse {
+                    files: check::check_all(&app),
+                })
+            }
+        };
+
+        let target = match body.app {
+            Some(name) => match app.projects.read().iter().find(|x| x.name == name) {
+                Some(i) => Some(i.project_path.to_owned()),
+                None => return misc::error_res("Invalid App"),
+            },
+            None => None,
+        };
+
+        let kind = body.kind.unwrap_or_else(|| check::detect_kind(&config));
+        let problems = match kind {
+            ConfigKind::Daemon => check::check_daemon(&config),
+            ConfigKind::Project => check::check_project(&app, &config, target.as_deref()),
+        };
+
+        misc::json_res(&ConfigCheckResponse {
+            files: vec![ConfigReport {
+                file: "<request>".to_owned(),
+                kind,
+                problems,
+            }],
+        })
+    });
+}
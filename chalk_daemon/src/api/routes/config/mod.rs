@@ -0,0 +1,13 @@
+use std::sync::Arc;
+
+use afire::Server;
+
+use crate::App;
+
+mod check;
+mod reload;
+
+pub fn attach(server: &mut Server, app: Arc<App>) {
+    check::attach(server, app.clone());
+    reload::attach(server, app);
+}
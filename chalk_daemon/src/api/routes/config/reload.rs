@@ -0,0 +1,29 @@
+use std::sync::Arc;
+
+use afire::{Method, Server};
+use chalk_client::models::TokenRequest;
+
+use crate::{
+    app::{LogLevel, LogSource},
+    misc::{self, BodyString, RealIp, ValidateType},
+    App,
+};
+
+pub fn attach(server: &mut Server, app: Arc<App>) {
+    server.route(Method::POST, "/config/reload", move |req| {
+        let body = serde_json::from_str::<TokenRequest>(&req.body_string()).unwrap();
+        if !ValidateType::Global.validate(app.clone(), body.token) {
+            return misc::error_res("Invalid Token");
+        }
+
+        app.log_from(
+            LogSource::Web,
+            LogLevel::Info,
+            format!("[{}] Reloading config", req.real_ip()),
+        );
+        match App::reload_config(app.clone()) {
+            Ok(i) => misc::json_res(&i),
+            Err(e) => misc::error_res(e),
+        }
+    });
+}
@@ -1,26 +1,16 @@
 use std::sync::Arc;
 
-use afire::{Content, Method, Response, Server};
-use serde_derive::Deserialize;
-use serde_json::json;
+use afire::{Method, Server};
+use chalk_client::models::{LogsRequest, LogsResponse};
 
 use crate::{
     misc::{self, BodyString, ValidateType},
     App,
 };
 
-#[derive(Deserialize)]
-struct RequestData {
-    token: String,
-    page: usize,
-    lines: usize,
-    end_time: Option<i64>,
-    rev: Option<bool>,
-}
-
 pub fn attach(server: &mut Server, app: Arc<App>) {
     server.route(Method::POST, "/logs", move |req| {
-        let body = serde_json::from_str::<RequestData>(&req.body_string()).unwrap();
+        let body = serde_json::from_str::<LogsRequest>(&req.body_string()).unwrap();
         if !ValidateType::Global.validate(app.clone(), body.token) {
             return misc::error_res("Invalid Token");
         }
@@ -29,6 +19,8 @@ pub fn attach(server: &mut Server, app: Arc<App>) {
         let filterd = logs
             .iter()
             .filter(|x| body.end_time.is_none() || x.time <= body.end_time.unwrap())
+            .filter(|x| body.level.map(|l| x.level >= l).unwrap_or(true))
+            .filter(|x| body.source.as_ref().map(|s| x.source == *s).unwrap_or(true))
             .collect::<Vec<_>>();
         let end = filterd.len() <= (body.page + 1) * body.lines;
 
@@ -39,19 +31,13 @@ pub fn attach(server: &mut Server, app: Arc<App>) {
             .skip(body.page * body.lines)
             .take(body.lines)
         {
-            out.push(json!({
-                "type": i.log_type.to_string(),
-                "text": i.data,
-                "time": i.time
-            }));
+            out.push((*i).into());
         }
 
         if let Some(true) = body.rev {
             out.reverse();
         }
 
-        Response::new()
-            .text(json!({ "logs": out, "end": end }))
-            .content(Content::JSON)
+        misc::json_res(&LogsResponse { logs: out, end })
     });
 }
@@ -5,13 +5,17 @@ use afire::Server;
 use crate::App;
 
 mod app;
+mod config;
 mod logs;
 mod ping;
+mod rescan;
 mod status;
 
 pub fn attach(server: &mut Server, app: Arc<App>) {
     app::attach(server, app.clone());
+    config::attach(server, app.clone());
     logs::attach(server, app.clone());
     ping::attach(server, app.clone());
+    rescan::attach(server, app.clone());
     status::attach(server, app);
 }
@@ -0,0 +1,28 @@
+use std::sync::Arc;
+
+use afire::{Method, Server};
+use chalk_client::models::{RescanResponse, TokenRequest};
+
+use crate::{
+    app::{LogLevel, LogSource},
+    misc::{self, BodyString, RealIp, ValidateType},
+    App, Project,
+};
+
+pub fn attach(server: &mut Server, app: Arc<App>) {
+    server.route(Method::POST, "/rescan", move |req| {
+        let body = serde_json::from_str::<TokenRequest>(&req.body_string()).unwrap();
+        if !ValidateType::Global.validate(app.clone(), body.token) {
+            return misc::error_res("Invalid Token");
+        }
+
+        let loaded = Project::rescan(app.clone());
+        app.log_from(
+            LogSource::Web,
+            LogLevel::Info,
+            format!("[{}] Rescaned apps ({} new)", req.real_ip(), loaded.len()),
+        );
+
+        misc::json_res(&RescanResponse { loaded })
+    });
+}
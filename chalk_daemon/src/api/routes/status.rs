@@ -1,22 +1,18 @@
 use std::sync::Arc;
 
-use afire::{Content, Method, Response, Server};
-use serde_derive::Deserialize;
-use serde_json::json;
+use afire::{Method, Server};
+use chalk_client::models::{
+    AppStatus, Disk, Load, Memory, Os, StatusResponse, SystemInfo, TokenRequest,
+};
 
 use crate::{
     misc::{self, BodyString, ValidateType},
     App, VERSION,
 };
 
-#[derive(Deserialize)]
-struct RequestData {
-    token: String,
-}
-
 pub fn attach(server: &mut Server, app: Arc<App>) {
     server.route(Method::GET, "/status", move |req| {
-        let body = serde_json::from_str::<RequestData>(&req.body_string()).unwrap();
+        let body = serde_json::from_str::<TokenRequest>(&req.body_string()).unwrap();
         if !ValidateType::Global.validate(app.clone(), body.token) {
             return misc::error_res("Invalid Token");
         }
@@ -32,49 +28,44 @@ pub fn attach(server: &mut Server, app: Arc<App>) {
         // App Status
         let mut apps = Vec::new();
         for i in app.projects.read().iter() {
-            apps.push(json!({
-                "name": i.name,
-                "status": *i.status.read()
-            }));
+            apps.push(AppStatus {
+                name: i.name.to_owned(),
+                status: *i.status.read(),
+                switching: i.next.lock().is_some(),
+            });
         }
 
         // Logs
         let mut logs = Vec::new();
         for i in app.logs.read().iter().take(20) {
-            logs.push(json!({
-                "type": i.log_type.to_string(),
-                "text": i.data,
-                "time": i.time
-            }));
+            logs.push(i.into());
         }
 
-        Response::new()
-            .text(json!({
-                "version": VERSION,
-                "uptime": app.uptime,
-                "system": {
-                    "disk": {
-                        "total": disk.total,
-                        "free": disk.free
-                    },
-                    "memory": {
-                        "total": mem.total,
-                        "free": mem.free
-                    },
-                    "load": {
-                        "1m": load.one,
-                        "5m": load.five,
-                        "15m": load.fifteen,
-                    },
-                    "os": {
-                        "type": os,
-                        "release": os_rel
-                    },
-                    "processes": proc
+        misc::json_res(&StatusResponse {
+            version: VERSION.to_owned(),
+            uptime: app.uptime,
+            system: SystemInfo {
+                disk: Disk {
+                    total: disk.total,
+                    free: disk.free,
+                },
+                memory: Memory {
+                    total: mem.total,
+                    free: mem.free,
+                },
+                load: Load {
+                    one: load.one,
+                    five: load.five,
+                    fifteen: load.fifteen,
+                },
+                os: Os {
+                    os_type: os,
+                    release: os_rel,
                 },
-                "apps": apps,
-                "logs": logs
-            }))
-            .content(Content::JSON)
+                processes: proc,
+            },
+            apps,
+            logs,
+        })
     });
 }
@@ -0,0 +1,425 @@
+//! Listener for release uploads.
+//! afire reads a whole request body into memory before any route sees it,
+//! so uploads are taken here instead: the token and `Content-Length` are checked first,
+//! then the body is streamed to a file in the app's `releases` folder.
+
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use chalk_client::models::{AppUploadResponse, BundleKind};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+use crate::{misc::ValidateType, project::DeployStrategy, App, LogLevel, LogSource};
+
+/// Longest request line or header line
+const MAX_LINE: u64 = 8 * 1024;
+/// Most headers in a request, or in a multipart part
+const MAX_HEADERS: usize = 100;
+/// How long a read can block before the upload is dropped
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// An error response
+struct Error(u16, String);
+
+impl<T: Into<String>> From<T> for Error {
+    fn from(from: T) -> Self {
+        Self(400, from.into())
+    }
+}
+
+/// Removes the upload file unless it was moved into a release
+struct TempFile(PathBuf);
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+/// Hashes everything written through it
+struct HashWriter<W: Write> {
+    inner: W,
+    hasher: Sha256,
+    written: u64,
+}
+
+impl<W: Write> Write for HashWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = self.inner.write(buf)?;
+        self.hasher.update(&buf[..len]);
+        self.written += len as u64;
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+struct Head {
+    method: String,
+    path: String,
+    headers: Vec<(String, String)>,
+}
+
+impl Head {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|x| x.0.eq_ignore_ascii_case(name))
+            .map(|x| x.1.as_str())
+    }
+}
+
+pub fn start(app: Arc<App>) {
+    thread::Builder::new()
+        .name("Upload".into())
+        .spawn(|| _start(app))
+        .unwrap();
+}
+
+fn _start(app: Arc<App>) {
+    let config = app.config.read();
+    let (host, port) = (config.api.host.to_owned(), config.api.upload_port);
+    drop(config);
+    let listener = TcpListener::bind((host.as_str(), port)).unwrap();
+
+    // Uploads are slow, so each gets a thread, up to `api.workers` at once
+    let active = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming().flatten() {
+        let (app, active) = (app.clone(), active.clone());
+        if active.fetch_add(1, Ordering::AcqRel) >= app.config.read().api.workers {
+            active.fetch_sub(1, Ordering::AcqRel);
+            respond(&stream, Err(Error(503, "Too many uploads".to_owned())));
+            continue;
+        }
+
+        thread::Builder::new()
+            .name("Upload".into())
+            .spawn(move || {
+                handle(&app, stream);
+                active.fetch_sub(1, Ordering::AcqRel);
+            })
+            .unwrap();
+    }
+}
+
+fn handle(app: &Arc<App>, stream: TcpStream) {
+    let _ = stream.set_read_timeout(Some(TIMEOUT));
+    let mut reader = BufReader::new(&stream);
+    let head = match read_head(&mut reader) {
+        Ok(i) => i,
+        Err(e) => return respond(&stream, Err(e)),
+    };
+
+    let ip = real_ip(&stream, &head);
+    let res = upload(app, &mut reader, &head, ip);
+    let status = res.as_ref().map(|_| 200).unwrap_or_else(|e| e.0);
+    app.log_from(
+        LogSource::Web,
+        LogLevel::Debug,
+        format!("[{ip}] {} {} ({status})", head.method, head.path),
+    );
+    respond(&stream, res.map(|x| json!(x)));
+}
+
+fn upload(
+    app: &Arc<App>,
+    body: &mut impl BufRead,
+    head: &Head,
+    ip: IpAddr,
+) -> Result<AppUploadResponse, Error> {
+    let name = match head.path.strip_prefix("/app/upload/") {
+        Some(i) if head.method == "POST" && !i.is_empty() => i.to_owned(),
+        _ => return Err(Error(404, "Not Found".to_owned())),
+    };
+
+    // The body is the raw binary or bundle (or a multipart form with it), so the token goes in a header
+    let token = head
+        .header("Authorization")
+        .and_then(|x| x.strip_prefix("Bearer "))
+        .unwrap_or_default()
+        .to_owned();
+    if !ValidateType::Scoped(name.to_owned()).validate(app.clone(), token.to_owned()) {
+        return Err("Invalid Token".into());
+    }
+    let uploader = ValidateType::uploader(app.clone(), token, ip);
+
+    // Checked before reading any of the body
+    let max_upload = app.config.read().api.max_upload;
+    let length = match head
+        .header("Content-Length")
+        .map(|x| x.trim().parse::<u64>())
+    {
+        Some(Ok(i)) if head.header("Transfer-Encoding").is_none() => i,
+        _ => return Err(Error(411, "Uploads need a Content-Length".to_owned())),
+    };
+    if length > max_upload * 1024 * 1024 {
+        return Err(Error(
+            413,
+            format!("Upload is over the {max_upload} MiB limit"),
+        ));
+    }
+
+    let expected = match head.header("X-Chalk-Sha256") {
+        Some(i) => i.to_ascii_lowercase(),
+        None => return Err("Missing X-Chalk-Sha256 header".into()),
+    };
+
+    let bundle = match head.header("X-Chalk-Bundle") {
+        Some(i) => match BundleKind::from_name(i) {
+            Some(i) => Some(i),
+            None => return Err("Unsupported bundle, use tar.gz or zip".into()),
+        },
+        None => None,
+    };
+
+    let project = match app.project(&name) {
+        Some(i) => i,
+        None => return Err("Invalid App".into()),
+    };
+
+    let file = TempFile(project.upload_path()?);
+    let mut out = HashWriter {
+        inner: File::create(&file.0).map_err(|e| format!("Error creating upload: {e}"))?,
+        hasher: Sha256::new(),
+        written: 0,
+    };
+    let mut body = body.take(length);
+    let read = match head.header("Content-Type").and_then(boundary) {
+        Some(i) => match multipart_file(&mut body, &i, &mut out) {
+            Ok(true) => Ok(()),
+            Ok(false) => return Err("No file in multipart body".into()),
+            Err(e) => Err(e),
+        },
+        None => match io::copy(&mut body, &mut out) {
+            Ok(i) if i < length => return Err("Upload ended early".into()),
+            Ok(_) => Ok(()),
+            Err(e) => Err(e),
+        },
+    };
+    read.and_then(|_| out.flush())
+        .map_err(|e| format!("Error reading upload: {e}"))?;
+
+    let size = out.written;
+    if hex::encode(out.hasher.finalize()) != expected {
+        return Err("Checksum mismatch".into());
+    }
+
+    let _deploying = project.deploy_lock.lock();
+
+    // Same rules as the `Update` action
+    let running = project.status.read().is_running();
+    if running && project.config.deploy.strategy != DeployStrategy::BlueGreen {
+        return Err("App is still running".into());
+    }
+
+    let previous = project.current_release();
+    let signature = head.header("X-Chalk-Signature");
+    let release = project.with_deploy_hooks(|| {
+        let release = project.add_upload(&file.0, expected, bundle, signature, &uploader)?;
+
+        if running {
+            project
+                .restart_or_revert(previous)
+                .map_err(|e| format!("New instance failed: {e}"))?;
+        }
+        Ok(release)
+    })?;
+
+    app.log_from(
+        LogSource::App(name.to_owned()),
+        LogLevel::Info,
+        format!("[{ip}] Uploaded release {release} of `{name}` ({size} bytes)"),
+    );
+
+    Ok(AppUploadResponse { release })
+}
+
+/// Reads the request line and headers
+fn read_head(reader: &mut impl BufRead) -> Result<Head, Error> {
+    let line = read_line(reader)?;
+    let mut parts = line.split(' ');
+    let (method, path) = match (parts.next(), parts.next()) {
+        (Some(method), Some(path)) => (method.to_owned(), path.to_owned()),
+        _ => return Err("Invalid request line".into()),
+    };
+
+    let mut headers = Vec::new();
+    loop {
+        let line = read_line(reader)?;
+        if line.is_empty() {
+            break;
+        }
+        if headers.len() >= MAX_HEADERS {
+            return Err("Too many headers".into());
+        }
+
+        match line.split_once(':') {
+            Some((name, value)) => headers.push((name.trim().to_owned(), value.trim().to_owned())),
+            None => return Err("Invalid header".into()),
+        }
+    }
+
+    Ok(Head {
+        method,
+        path,
+        headers,
+    })
+}
+
+/// Reads a line without its line ending, failing if its over [`MAX_LINE`]
+fn read_line(reader: &mut impl BufRead) -> Result<String, Error> {
+    let mut line = Vec::new();
+    reader
+        .by_ref()
+        .take(MAX_LINE)
+        .read_until(b'\n', &mut line)
+        .map_err(|e| format!("Error reading request: {e}"))?;
+    if line.pop() != Some(b'\n') {
+        return Err("Request line too long or cut off".into());
+    }
+    if line.last() == Some(&b'\r') {
+        line.pop();
+    }
+
+    Ok(String::from_utf8_lossy(&line).into_owned())
+}
+
+/// Same as [`crate::misc::RealIp`], a proxy on this machine can pass on the client's address
+fn real_ip(stream: &TcpStream, head: &Head) -> IpAddr {
+    let ip = match stream.peer_addr() {
+        Ok(i) => i.ip(),
+        Err(_) => return IpAddr::from([0, 0, 0, 0]),
+    };
+
+    match head.header("X-Forwarded-For").map(|x| x.parse()) {
+        Some(Ok(i)) if ip.is_loopback() => i,
+        _ => ip,
+    }
+}
+
+fn respond(mut stream: &TcpStream, res: Result<serde_json::Value, Error>) {
+    let (status, body) = match res {
+        Ok(i) => (200, i),
+        Err(Error(status, e)) => (status, json!({ "error": e })),
+    };
+    let reason = match status {
+        200 => "OK",
+        404 => "Not Found",
+        411 => "Length Required",
+        413 => "Payload Too Large",
+        503 => "Service Unavailable",
+        _ => "Bad Request",
+    };
+
+    let body = body.to_string();
+    let res = format!(
+        "HTTP/1.1 {status} {reason}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    let _ = stream.write_all(res.as_bytes());
+}
+
+/// Gets the boundary of a `multipart/form-data` content type
+fn boundary(content_type: &str) -> Option<String> {
+    let (kind, params) = content_type.split_once(';')?;
+    if !kind.trim().eq_ignore_ascii_case("multipart/form-data") {
+        return None;
+    }
+
+    params
+        .split(';')
+        .filter_map(|x| x.trim().split_once('='))
+        .find(|x| x.0.eq_ignore_ascii_case("boundary"))
+        .map(|x| x.1.trim_matches('"').to_owned())
+}
+
+/// Copies the contents of the first file in a multipart body to `out`.
+/// Returns false if there is no file in it.
+fn multipart_file(
+    body: &mut impl BufRead,
+    boundary: &str,
+    out: &mut impl Write,
+) -> io::Result<bool> {
+    let start = format!("--{boundary}");
+    let delimiter = format!("\r\n--{boundary}");
+
+    // Anything before the first boundary is ignored
+    loop {
+        let line = read_line(body).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.1))?;
+        if line == start {
+            break;
+        }
+    }
+
+    for _ in 0..MAX_HEADERS {
+        let mut is_file = false;
+        loop {
+            let line =
+                read_line(body).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.1))?;
+            if line.is_empty() {
+                break;
+            }
+            is_file |= line.to_ascii_lowercase().contains("filename=");
+        }
+
+        if is_file {
+            return copy_until(body, delimiter.as_bytes(), out);
+        }
+        if !copy_until(body, delimiter.as_bytes(), &mut io::sink())? {
+            return Ok(false);
+        }
+
+        // The last delimiter is followed by `--`
+        let mut end = [0; 2];
+        body.read_exact(&mut end)?;
+        if &end == b"--" {
+            return Ok(false);
+        }
+    }
+
+    Ok(false)
+}
+
+/// Copies from `reader` to `out` until `delimiter`, which is skipped over.
+/// Returns false if the reader ended first.
+fn copy_until(
+    reader: &mut impl BufRead,
+    delimiter: &[u8],
+    out: &mut impl Write,
+) -> io::Result<bool> {
+    // The end of the last read, in case the delimiter starts in it
+    let mut pending = Vec::new();
+    loop {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            return Ok(false);
+        }
+
+        let (old, len) = (pending.len(), buf.len());
+        pending.extend_from_slice(buf);
+        if let Some(i) = find(&pending, delimiter) {
+            out.write_all(&pending[..i])?;
+            reader.consume(i + delimiter.len() - old);
+            return Ok(true);
+        }
+        reader.consume(len);
+
+        let keep = pending.len().min(delimiter.len() - 1);
+        let done = pending.len() - keep;
+        out.write_all(&pending[..done])?;
+        pending.drain(..done);
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|x| x == needle)
+}
@@ -1,51 +1,53 @@
 use std::fmt::{self, Display, Formatter};
 use std::fs::{self, OpenOptions};
 use std::io::Write;
+use std::path::PathBuf;
 use std::process;
 use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
 
+use chalk_client::models::{self, ConfigReloadResponse, ProblemLevel};
 use chrono::{TimeZone, Utc};
 use colored::Colorize;
 use directories::ProjectDirs;
 use parking_lot::RwLock;
 
-use crate::config::Config;
+use crate::check;
+use crate::config::{Config, Logging};
 use crate::Project;
+pub use models::{LogLevel, LogSource};
 
 pub struct App {
     // == App ==
     pub app_dir: ProjectDirs,
-    pub config: Config,
+    pub config: RwLock<Config>,
     pub uptime: i64,
 
     // == Logs ==
     pub logs: RwLock<Vec<Log>>,
+    /// Copy of the log config, so logging never waits on the config lock
+    pub log_levels: RwLock<Logging>,
     pub last_log_save: AtomicU64,
     pub log_save_index: AtomicUsize,
 
     // == Projects ==
-    pub projects: RwLock<Vec<Project>>,
+    pub projects: RwLock<Vec<Arc<Project>>>,
     pub last_exit_try: AtomicU64,
 }
 
 pub struct Log {
-    pub log_type: LogType,
+    pub level: LogLevel,
+    pub source: LogSource,
     pub time: i64,
     pub data: String,
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
-pub enum LogType {
-    Error,
-    Info,
-}
-
 impl App {
     pub fn new() -> Self {
         let app_dir = ProjectDirs::from("com", "connorcode", "chalk").unwrap();
 
         let config_path = app_dir.preference_dir().join("config.toml");
-        let config = match fs::read_to_string(&config_path) {
+        let config: Config = match fs::read_to_string(&config_path) {
             Ok(i) => toml::from_str(&i).unwrap(),
             Err(_) => {
                 fs::create_dir_all(config_path.parent().unwrap()).unwrap();
@@ -59,9 +61,15 @@ impl App {
             }
         };
 
+        Self::from_config(app_dir, config)
+    }
+
+    /// Makes the app state around an already loaded config
+    pub fn from_config(app_dir: ProjectDirs, config: Config) -> Self {
         Self {
             app_dir,
-            config,
+            log_levels: RwLock::new(config.log),
+            config: RwLock::new(config),
             uptime: Utc::now().timestamp(),
 
             logs: RwLock::new(Vec::new()),
@@ -73,20 +81,140 @@ impl App {
         }
     }
 
-    pub fn log<T: AsRef<str>>(&self, log_type: LogType, text: T) {
-        self.logs.write().push(Log {
-            log_type,
+    /// Finds a loaded project by its name.
+    /// The list is not kept locked, so the project can be used for slow work (EX: deploys).
+    pub fn project(&self, name: &str) -> Option<Arc<Project>> {
+        self.projects
+            .read()
+            .iter()
+            .find(|x| x.name == name)
+            .cloned()
+    }
+
+    /// The daemon config file
+    pub fn config_path(&self) -> PathBuf {
+        self.app_dir.preference_dir().join("config.toml")
+    }
+
+    /// The folder projects are loaded from
+    pub fn apps_path(&self) -> PathBuf {
+        self.app_dir
+            .preference_dir()
+            .join(&self.config.read().app_dir)
+    }
+
+    /// Re-reads the daemon config and applies the settings that can change while running.
+    /// Apps are left running.
+    pub fn reload_config(app: Arc<App>) -> Result<ConfigReloadResponse, String> {
+        let raw = fs::read_to_string(app.config_path())
+            .map_err(|e| format!("Error reading config: {e}"))?;
+        if let Some(e) = check::check_daemon(&raw)
+            .into_iter()
+            .find(|x| x.level == ProblemLevel::Error)
+        {
+            return Err(format!("Invalid config: {e}"));
+        }
+        let mut new = toml::from_str::<Config>(&raw).map_err(|e| e.to_string())?;
+
+        let mut out = ConfigReloadResponse::default();
+        let mut config = app.config.write();
+
+        // Keep settings that need a restart
+        for (name, changed) in [
+            ("app_dir", config.app_dir != new.app_dir),
+            ("api.host", config.api.host != new.api.host),
+            ("api.port", config.api.port != new.api.port),
+            ("api.workers", config.api.workers != new.api.workers),
+            ("watch.enabled", config.watch.enabled != new.watch.enabled),
+        ] {
+            if changed {
+                out.restart_required.push(name.to_owned());
+            }
+        }
+        new.app_dir = config.app_dir.to_owned();
+        new.api.host = config.api.host.to_owned();
+        new.api.port = config.api.port;
+        new.api.workers = config.api.workers;
+        new.watch.enabled = config.watch.enabled;
+
+        for (name, changed) in [
+            ("api.token", config.api.token != new.api.token),
+            ("task_poll", config.task_poll != new.task_poll),
+            ("log.console", config.log.console != new.log.console),
+            ("log.file", config.log.file != new.log.file),
+            (
+                "watch.config_policy",
+                config.watch.config_policy != new.watch.config_policy,
+            ),
+        ] {
+            if changed {
+                out.applied.push(name.to_owned());
+            }
+        }
+
+        *app.log_levels.write() = new.log;
+        *config = new;
+        drop(config);
+
+        // Update project list
+        out.unloaded = Project::unload_missing(app.clone());
+        out.loaded = Project::rescan(app.clone());
+
+        app.log(
+            LogLevel::Info,
+            format!("Config reloaded. Applied: [{}]", out.applied.join(", ")),
+        );
+        if !out.restart_required.is_empty() {
+            app.log(
+                LogLevel::Warn,
+                format!(
+                    "Restart needed to apply: [{}]",
+                    out.restart_required.join(", ")
+                ),
+            );
+        }
+
+        Ok(out)
+    }
+
+    /// Logs something the daemon itself did
+    pub fn log<T: AsRef<str>>(&self, level: LogLevel, text: T) {
+        self.log_from(LogSource::Daemon, level, text);
+    }
+
+    /// Logs an entry from `source`.
+    /// Entries below both the console and file level are dropped.
+    pub fn log_from<T: AsRef<str>>(&self, source: LogSource, level: LogLevel, text: T) {
+        let levels = *self.log_levels.read();
+        if level < levels.console && level < levels.file {
+            return;
+        }
+
+        let log = Log {
+            level,
+            source,
             data: text.as_ref().to_string(),
             time: Utc::now().timestamp(),
-        });
+        };
 
-        // DEBUG!?
-        if log_type == LogType::Error {
-            println!("{}", text.as_ref().red());
-            return;
+        if level >= levels.console {
+            let line = match &log.source {
+                LogSource::Daemon => log.data.to_owned(),
+                LogSource::Web => format!("[WEB] {}", log.data),
+                LogSource::App(i) => format!("[{i}] {}", log.data),
+            };
+            println!(
+                "{}",
+                match level {
+                    LogLevel::Trace | LogLevel::Debug => line.dimmed(),
+                    LogLevel::Info => line.normal(),
+                    LogLevel::Warn => line.yellow(),
+                    LogLevel::Error => line.red(),
+                }
+            );
         }
 
-        println!("{}", text.as_ref());
+        self.logs.write().push(log);
     }
 
     pub fn log_tick(&self, force: bool) {
@@ -113,9 +241,11 @@ impl App {
             .open(log_file)
             .unwrap();
 
+        let file_level = self.log_levels.read().file;
         let mut to_save = logs
             .iter()
             .skip(last_save_index)
+            .filter(|x| x.level >= file_level)
             .map(|x| x.to_string())
             .collect::<Vec<_>>();
         to_save.push("".to_owned());
@@ -127,21 +257,24 @@ impl App {
     }
 }
 
-impl Display for LogType {
-    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
-        f.write_str(match self {
-            LogType::Error => "error",
-            LogType::Info => "info",
-        })
+impl From<&Log> for models::Log {
+    fn from(from: &Log) -> Self {
+        Self {
+            level: from.level,
+            source: from.source.to_owned(),
+            text: from.data.to_owned(),
+            time: from.time,
+        }
     }
 }
 
 impl Display for Log {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         f.write_fmt(format_args!(
-            "[{}] [{}] {}",
+            "[{}] [{}] [{}] {}",
             Utc.timestamp_opt(self.time, 0).unwrap().format("%H:%M:%S"),
-            self.log_type,
+            self.level,
+            self.source,
             self.data
         ))
     }
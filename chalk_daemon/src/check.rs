@@ -0,0 +1,344 @@
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+use chalk_client::{
+    models::{ConfigKind, ConfigProblem, ConfigReport},
+    signing,
+};
+
+use crate::{
+    config::Config,
+    misc,
+    project::{DeployStrategy, GitStrategy, ProjectConfig},
+    App, Project,
+};
+
+// == Daemon ==
+
+pub fn check_daemon(raw: &str) -> Vec<ConfigProblem> {
+    let config = match toml::from_str::<Config>(raw) {
+        Ok(i) => i,
+        Err(e) => return vec![parse_error(&e, raw)],
+    };
+    let mut out = Vec::new();
+
+    if config.api.token.is_empty() {
+        out.push(ConfigProblem::error("`api.token` is empty"));
+    } else if config.api.token.len() < 10 {
+        out.push(ConfigProblem::warning("`api.token` is very short"));
+    }
+
+    if config.api.workers == 0 {
+        out.push(ConfigProblem::error("`api.workers` must be at least 1"));
+    }
+
+    if config.task_poll == 0 {
+        out.push(ConfigProblem::error("`task_poll` must be at least 1"));
+    }
+
+    out
+}
+
+// == Project ==
+
+/// Checks a project config.
+/// `target` is the folder of the app the config is for, if its not a new app.
+pub fn check_project(app: &App, raw: &str, target: Option<&Path>) -> Vec<ConfigProblem> {
+    let config = match toml::from_str::<ProjectConfig>(raw) {
+        Ok(i) => i,
+        Err(e) => return vec![parse_error(&e, raw)],
+    };
+    let mut out = Vec::new();
+
+    // New apps are put in a folder with their name
+    if target.is_none() && !Project::valid_name(&config.name) {
+        out.push(ConfigProblem::error(format!(
+            "`name` ({}) can only use letters, numbers, `-`, `_` and `.`",
+            config.name
+        )));
+    }
+    let path = match target {
+        Some(i) => i.to_owned(),
+        None => app.apps_path().join(&config.name),
+    };
+
+    // Names and tokens
+    let projects = app.projects.read();
+    let others = projects
+        .iter()
+        .filter(|x| Some(x.project_path.as_path()) != target);
+    if let Err(e) = Project::check_unique(&config, others, app) {
+        out.push(ConfigProblem::error(e));
+    }
+    drop(projects);
+
+    // Run config
+    let run_path = Path::new(&config.run.path);
+    if !misc::inside(run_path) {
+        out.push(ConfigProblem::error(format!(
+            "`run.path` ({}) must be inside the app folder",
+            config.run.path
+        )));
+    } else if path.exists() {
+        // Bundles run from `current`, which only exists after the first deploy
+        match fs::metadata(path.join(run_path)) {
+            Ok(i) if !i.is_dir() => out.push(ConfigProblem::error(format!(
+                "`run.path` ({}) is not a folder",
+                config.run.path
+            ))),
+            Ok(_) => {}
+            Err(_) => out.push(ConfigProblem::warning(format!(
+                "`run.path` ({}) does not exist yet",
+                config.run.path
+            ))),
+        }
+    }
+
+    let binary = path.join(&config.run.command);
+    match fs::metadata(&binary) {
+        Ok(i) if i.permissions().mode() & 0o111 == 0 => out.push(ConfigProblem::warning(format!(
+            "`run.command` ({}) is not executable",
+            config.run.command
+        ))),
+        Ok(_) => {}
+        Err(_) => out.push(ConfigProblem::warning(format!(
+            "`run.command` ({}) does not exist yet",
+            config.run.command
+        ))),
+    }
+
+    // Git config
+    let git = &config.git;
+    if (git.token.is_some() || git.ssh_key_file.is_some()) && git.username.is_none() {
+        out.push(ConfigProblem::error(
+            "`git.username` is needed to use `git.token` or `git.ssh_key_file`",
+        ));
+    }
+
+    if let Some(i) = &git.ssh_key_file {
+        if !Path::new(i).exists() {
+            out.push(ConfigProblem::error(format!(
+                "`git.ssh_key_file` ({i}) does not exist"
+            )));
+        }
+    }
+
+    match git.depth {
+        Some(0) => out.push(ConfigProblem::error("`git.depth` must be at least 1")),
+        Some(_) if git.strategy == GitStrategy::Merge => out.push(ConfigProblem::warning(
+            "`git.depth` with the `merge` strategy can fail to find a merge base, use `reset`",
+        )),
+        _ => {}
+    }
+
+    // Build config
+    if let Some(i) = &config.build {
+        if config.git.repo.is_none() {
+            out.push(ConfigProblem::error("`build` needs `git.repo` to be set"));
+        }
+
+        if !misc::inside(Path::new(&i.path)) {
+            out.push(ConfigProblem::error(format!(
+                "`build.path` ({}) must be inside the repo",
+                i.path
+            )));
+        }
+
+        if !misc::inside(Path::new(&i.artifact)) {
+            out.push(ConfigProblem::error(format!(
+                "`build.artifact` ({}) must be inside the repo",
+                i.artifact
+            )));
+        }
+
+        if i.timeout == 0 {
+            out.push(ConfigProblem::error("`build.timeout` must be at least 1"));
+        }
+    }
+
+    // Deploy config
+    let deploy = &config.deploy;
+    if deploy.strategy == DeployStrategy::BlueGreen
+        && deploy.ready_port.is_none()
+        && deploy.ready_url.is_none()
+        && !config.notify.ready
+    {
+        out.push(ConfigProblem::error(
+            "`deploy.strategy` blue_green needs `deploy.ready_port`, `deploy.ready_url` or `notify.ready`",
+        ));
+    }
+
+    if let Some(i) = &deploy.ready_url {
+        if !i.starts_with("http://") {
+            out.push(ConfigProblem::error(format!(
+                "`deploy.ready_url` ({i}) must be an http:// url"
+            )));
+        }
+    }
+
+    if deploy.ready_timeout == 0 {
+        out.push(ConfigProblem::error(
+            "`deploy.ready_timeout` must be at least 1",
+        ));
+    }
+
+    if config.releases.keep == 0 {
+        out.push(ConfigProblem::error("`releases.keep` must be at least 1"));
+    }
+
+    for i in &config.releases.preserve {
+        let preserve = Path::new(i);
+        if !misc::inside(preserve) || preserve.components().next().is_none() {
+            out.push(ConfigProblem::error(format!(
+                "`releases.preserve` path ({i}) must be inside the release"
+            )));
+        }
+    }
+
+    for i in &config.releases.trusted_keys {
+        if signing::parse_public_key(i).is_none() {
+            out.push(ConfigProblem::error(format!(
+                "`releases.trusted_keys` key ({i}) is not a hex encoded ed25519 public key"
+            )));
+        }
+    }
+
+    // Webhook config
+    if let Some(i) = &config.webhook {
+        if config.git.repo.is_none() {
+            out.push(ConfigProblem::error("`webhook` needs `git.repo` to be set"));
+        }
+
+        if i.secret.is_empty() {
+            out.push(ConfigProblem::error("`webhook.secret` is empty"));
+        }
+
+        if i.branch.is_none() && i.tag.is_none() {
+            out.push(ConfigProblem::warning(
+                "`webhook` has no `branch` or `tag`, so no pushes will be deployed",
+            ));
+        }
+    }
+
+    // Hooks config
+    if config.hooks.timeout == 0 {
+        out.push(ConfigProblem::error("`hooks.timeout` must be at least 1"));
+    }
+
+    // Notify config
+    if config.notify.watchdog == Some(0) {
+        out.push(ConfigProblem::error("`notify.watchdog` must be at least 1"));
+    }
+
+    // Control config
+    if let Some(i) = &config.control {
+        if !i.url.starts_with("http://") {
+            out.push(ConfigProblem::error("`control.url` must be an http:// url"));
+        }
+
+        if i.timeout == 0 {
+            out.push(ConfigProblem::error("`control.timeout` must be at least 1"));
+        }
+    }
+
+    out
+}
+
+// == Dry Run ==
+
+/// Checks the daemon config and all app configs on disk, as if they were being loaded
+pub fn check_all(app: &App) -> Vec<ConfigReport> {
+    let mut out = Vec::new();
+
+    let config_path = app.config_path();
+    out.push(ConfigReport {
+        file: config_path.to_string_lossy().into_owned(),
+        kind: ConfigKind::Daemon,
+        problems: match fs::read_to_string(&config_path) {
+            Ok(i) => check_daemon(&i),
+            Err(e) => vec![ConfigProblem::error(format!("Error reading config: {e}"))],
+        },
+    });
+
+    let dirs = match fs::read_dir(app.apps_path()) {
+        Ok(i) => i,
+        Err(_) => return out,
+    };
+
+    for i in dirs.filter_map(|x| x.ok()).map(|x| x.path()) {
+        if !i.is_dir() {
+            continue;
+        }
+
+        let config_path = i.join("config.toml");
+        out.push(ConfigReport {
+            file: config_path.to_string_lossy().into_owned(),
+            kind: ConfigKind::Project,
+            problems: match fs::read_to_string(&config_path) {
+                Ok(raw) => check_project(app, &raw, Some(&i)),
+                Err(e) => vec![ConfigProblem::error(format!("Error reading config: {e}"))],
+            },
+        });
+    }
+
+    out
+}
+
+/// Guesses the type of a config by looking for the daemons `[api]` table
+pub fn detect_kind(raw: &str) -> ConfigKind {
+    match toml::from_str::<toml::Table>(raw) {
+        Ok(i) if i.contains_key("api") => ConfigKind::Daemon,
+        _ => ConfigKind::Project,
+    }
+}
+
+// == Misc ==
+
+fn parse_error(e: &toml::de::Error, raw: &str) -> ConfigProblem {
+    let mut out = ConfigProblem::error(e.message().trim());
+
+    if let Some(span) = e.span() {
+        let before = &raw[..span.start.min(raw.len())];
+        let line_start = before.rfind('\n').map(|x| x + 1).unwrap_or(0);
+        out.line = Some(before.matches('\n').count() + 1);
+        out.column = Some(before[line_start..].chars().count() + 1);
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use chalk_client::models::ProblemLevel;
+
+    use super::*;
+
+    fn error_at(raw: &str) -> (Option<usize>, Option<usize>) {
+        let e = toml::from_str::<toml::Value>(raw).unwrap_err();
+        let problem = parse_error(&e, raw);
+        assert_eq!(problem.level, ProblemLevel::Error);
+        (problem.line, problem.column)
+    }
+
+    #[test]
+    fn parse_error_position() {
+        assert_eq!(error_at("a = 1\nb = nope\n"), (Some(2), Some(5)));
+        assert_eq!(error_at("b = ?"), (Some(1), Some(5)));
+    }
+
+    #[test]
+    fn parse_error_counts_chars() {
+        // `é` is two bytes, but one column
+        assert_eq!(error_at("a = \"é\"\nb = \"é\" ?\n"), (Some(2), Some(9)));
+        assert_eq!(error_at("a = \"é\" ?"), (Some(1), Some(9)));
+    }
+
+    #[test]
+    fn daemon_type_error_position() {
+        let raw = "app_dir = \"./apps\"\ntask_poll = \"often\"\n";
+        let problems = check_daemon(raw);
+        assert_eq!(problems.len(), 1);
+        assert_eq!((problems[0].line, problems[0].column), (Some(2), Some(13)));
+    }
+}
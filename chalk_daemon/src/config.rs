@@ -1,3 +1,4 @@
+use chalk_client::models::LogLevel;
 use rand::prelude::*;
 use serde_derive::{Deserialize, Serialize};
 
@@ -9,6 +10,14 @@ pub struct Config {
 
     // Api Config
     pub api: Api,
+
+    // Watcher Config
+    #[serde(default)]
+    pub watch: Watch,
+
+    // Log Config
+    #[serde(default)]
+    pub log: Logging,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -17,6 +26,74 @@ pub struct Api {
     pub host: String,
     pub port: u16,
     pub workers: usize,
+    /// Port for release uploads.
+    /// afire reads a whole request into memory before any route sees it, so uploads have their own listener that streams them to disk.
+    #[serde(default = "default_upload_port")]
+    pub upload_port: u16,
+    /// Largest upload accepted, in MiB.
+    /// Checked against `Content-Length` before any of the body is read.
+    #[serde(default = "default_max_upload")]
+    pub max_upload: u64,
+    /// Most a bundle can unpack to, in MiB
+    #[serde(default = "default_max_unpacked")]
+    pub max_unpacked: u64,
+}
+
+fn default_upload_port() -> u16 {
+    3402
+}
+
+fn default_max_upload() -> u64 {
+    100
+}
+
+fn default_max_unpacked() -> u64 {
+    1024
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Watch {
+    /// Watch the apps folder for new / removed apps and config changes
+    pub enabled: bool,
+    /// When a changed config is applied to a running app
+    pub config_policy: ConfigPolicy,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigPolicy {
+    /// Stage the new config and apply it the next time the app is started
+    Restart,
+    /// Restart the app with the new config right away
+    Immediate,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Logging {
+    /// Lowest level printed to the console
+    pub console: LogLevel,
+    /// Lowest level saved to the log files
+    pub file: LogLevel,
+}
+
+impl Default for Logging {
+    fn default() -> Self {
+        Self {
+            console: LogLevel::Info,
+            file: LogLevel::Debug,
+        }
+    }
+}
+
+impl Default for Watch {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            config_policy: ConfigPolicy::Restart,
+        }
+    }
 }
 
 impl Default for Config {
@@ -34,7 +111,13 @@ impl Default for Config {
                 host: "localhost".to_owned(),
                 port: 3401,
                 workers: 10,
+                upload_port: default_upload_port(),
+                max_upload: default_max_upload(),
+                max_unpacked: default_max_unpacked(),
             },
+
+            watch: Watch::default(),
+            log: Logging::default(),
         }
     }
 }
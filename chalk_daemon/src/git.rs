@@ -0,0 +1,465 @@
+use std::path::Path;
+
+use chalk_client::models::Revision;
+use git2::{
+    build::{CheckoutBuilder, RepoBuilder},
+    BranchType, Commit, Cred, CredentialType, FetchOptions, Oid, RemoteCallbacks, Repository,
+    ResetType, StatusOptions, SubmoduleUpdateOptions,
+};
+
+use crate::project::{GitStrategy, ProjectGitConfig};
+
+/// What to pull into an app's repo
+pub struct Update<'a> {
+    /// Git remote (EX: origin)
+    pub remote: &'a str,
+    /// Refspec to fetch (EX: main or refs/tags/v1.0)
+    pub fetch: &'a str,
+    /// Local branch to merge into
+    pub branch: &'a str,
+    /// Commit / Tag to checkout instead of FETCH_HEAD
+    pub checkout: Option<&'a str>,
+    /// Discard local changes in the repo
+    pub force: bool,
+}
+
+/// Clones the app's repo to `repo_path` if needed, then fetches and applies the update with `git.strategy`.
+/// Trees with local changes are only touched if `force` is set.
+/// Checkouts are done by libgit2, so filters like LFS are never run.
+/// Returns false if there were merge conflicts, which leave the tree as it was.
+pub fn update(
+    config: &ProjectGitConfig,
+    repo_path: &Path,
+    repo_url: &str,
+    update: &Update,
+) -> Result<bool, git2::Error> {
+    if !repo_path.exists() {
+        let mut checkout_bld = CheckoutBuilder::new();
+        if update.force {
+            checkout_bld.force();
+        }
+
+        RepoBuilder::new()
+            .with_checkout(checkout_bld)
+            .fetch_options(fetch_options(config))
+            .clone(repo_url, repo_path)?;
+    }
+
+    let repo = Repository::open(repo_path)?;
+    if !update.force && dirty(&repo) {
+        return Err(git2::Error::from_str(
+            "Repo has local changes, use `force` to discard them",
+        ));
+    }
+
+    let mut remote = repo.find_remote(update.remote)?;
+    remote.fetch(&[update.fetch], Some(&mut fetch_options(config)), None)?;
+
+    let updated = match config.strategy {
+        GitStrategy::Merge => {
+            let fetch_commit = match update.checkout {
+                Some(i) => {
+                    repo.reference_to_annotated_commit(&repo.resolve_reference_from_short_name(i)?)?
+                }
+                None => repo.find_annotated_commit(fetch_head(&repo)?)?,
+            };
+            do_merge(&repo, update.branch, fetch_commit)?
+        }
+        GitStrategy::Reset => {
+            let commit = match update.checkout {
+                Some(i) => repo.revparse_single(i)?.peel_to_commit()?,
+                None => repo.find_commit(fetch_head(&repo)?)?,
+            };
+            hard_reset(&repo, update, &commit)?;
+            true
+        }
+    };
+
+    if updated && config.submodules {
+        update_submodules(config, &repo)?;
+    }
+
+    Ok(updated)
+}
+
+/// Hash of the commit checked out in a repo
+pub fn head_commit(path: &Path) -> Option<String> {
+    let repo = Repository::open(path).ok()?;
+    let commit = repo.head().ok()?.peel_to_commit().ok()?;
+    Some(commit.id().to_string())
+}
+
+/// Info on what is checked out in a repo.
+/// Ahead / behind counts are against the last fetch, the remote is not contacted.
+pub fn revision(path: &Path) -> Option<Revision> {
+    let repo = Repository::open(path).ok()?;
+    let head = repo.head().ok()?;
+    let commit = head.peel_to_commit().ok()?;
+    let branch = match head.is_branch() {
+        true => head.shorthand().map(str::to_owned),
+        false => None,
+    };
+
+    let dirty = dirty(&repo);
+    let upstream = branch
+        .as_deref()
+        .and_then(|x| repo.find_branch(x, BranchType::Local).ok()?.upstream().ok());
+    let (upstream, ahead, behind) = match upstream {
+        Some(i) => {
+            let (ahead, behind) = i
+                .get()
+                .target()
+                .and_then(|x| repo.graph_ahead_behind(commit.id(), x).ok())
+                .unwrap_or_default();
+            (i.name().ok().flatten().map(str::to_owned), ahead, behind)
+        }
+        None => (None, 0, 0),
+    };
+
+    let author = commit.author().name().unwrap_or_default().to_owned();
+    Some(Revision {
+        commit: commit.id().to_string(),
+        branch,
+        message: commit.summary().unwrap_or_default().to_owned(),
+        author,
+        time: commit.time().seconds(),
+        dirty,
+        upstream,
+        ahead,
+        behind,
+    })
+}
+
+/// The fetched commit, `FETCH_HEAD` also lists any tags that were downloaded
+fn fetch_head(repo: &Repository) -> Result<Oid, git2::Error> {
+    let mut out = None;
+    // Stopping the loop early makes it return an error
+    let _ = repo.fetchhead_foreach(|_, _, id, is_merge| {
+        if is_merge {
+            out = Some(*id);
+        }
+        !is_merge
+    });
+
+    out.ok_or_else(|| git2::Error::from_str("Nothing was fetched"))
+}
+
+/// Checks if tracked files have uncommitted changes
+fn dirty(repo: &Repository) -> bool {
+    let mut options = StatusOptions::new();
+    options.include_untracked(false).include_ignored(false);
+    repo.statuses(Some(&mut options))
+        .map(|x| !x.is_empty())
+        .unwrap_or(false)
+}
+
+/// Moves the local branch (or a detached head for tags and checkouts) to a commit,
+/// then makes the index and tree match it exactly
+fn hard_reset(repo: &Repository, update: &Update, commit: &Commit) -> Result<(), git2::Error> {
+    let message = format!("chalk: reset to {}", commit.id());
+    if update.checkout.is_none() && !update.fetch.starts_with("refs/tags/") {
+        let refname = format!("refs/heads/{}", update.branch);
+        repo.reference(&refname, commit.id(), true, &message)?;
+        repo.set_head(&refname)?;
+    } else {
+        repo.set_head_detached(commit.id())?;
+    }
+
+    repo.reset(
+        commit.as_object(),
+        ResetType::Hard,
+        Some(CheckoutBuilder::new().force()),
+    )
+}
+
+fn update_submodules(config: &ProjectGitConfig, repo: &Repository) -> Result<(), git2::Error> {
+    for mut i in repo.submodules()? {
+        let mut options = SubmoduleUpdateOptions::new();
+        options.fetch(auth_callback(config));
+        i.update(true, Some(&mut options))?;
+
+        if let Ok(i) = i.open() {
+            update_submodules(config, &i)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Fetch options with auth and `git.depth`
+fn fetch_options(config: &ProjectGitConfig) -> FetchOptions<'_> {
+    let mut options = auth_callback(config);
+    if let Some(i) = config.depth {
+        options.depth(i as i32);
+    }
+    options
+}
+
+fn auth_callback(config: &ProjectGitConfig) -> FetchOptions<'_> {
+    let mut callbacks = RemoteCallbacks::new();
+    callbacks.credentials(|_url, username_from_url, allowed_types| {
+        let username = config
+            .username
+            .as_ref()
+            .expect("No project git username defined");
+
+        let token = config.token.as_ref();
+        let ssh_key = config.ssh_key_file.as_ref();
+
+        if allowed_types.contains(CredentialType::SSH_MEMORY) {
+            if let Some(i) = ssh_key {
+                return Cred::ssh_key(
+                    username_from_url.unwrap_or(username),
+                    None,
+                    Path::new(i),
+                    None,
+                );
+            }
+
+            panic!("Tried to use ssh auth, no private key defined");
+        }
+
+        if allowed_types.contains(CredentialType::USER_PASS_PLAINTEXT) {
+            if let Some(i) = token {
+                return Cred::userpass_plaintext(username_from_url.unwrap_or(username), i);
+            }
+
+            panic!("Tried to use token auth, no token defined");
+        }
+
+        panic!("No valid git auth found")
+    });
+
+    let mut fo = FetchOptions::new();
+    fo.remote_callbacks(callbacks);
+    fo.download_tags(git2::AutotagOption::All);
+    fo
+}
+
+// == Merging ==
+// Modified from https://github.com/rust-lang/git2-rs examples
+
+fn do_merge<'a>(
+    repo: &'a Repository,
+    remote_branch: &str,
+    fetch_commit: git2::AnnotatedCommit<'a>,
+) -> Result<bool, git2::Error> {
+    let analysis = repo.merge_analysis(&[&fetch_commit])?;
+
+    if analysis.0.is_fast_forward() {
+        let refname = format!("refs/heads/{remote_branch}");
+        match repo.find_reference(&refname) {
+            Ok(mut r) => {
+                let name = match r.name() {
+                    Some(s) => s.to_string(),
+                    None => String::from_utf8_lossy(r.name_bytes()).to_string(),
+                };
+                r.set_target(
+                    fetch_commit.id(),
+                    &format!(
+                        "Fast-Forward: Setting {} to id: {}",
+                        name,
+                        fetch_commit.id()
+                    ),
+                )?;
+                repo.set_head(&name)?;
+                repo.checkout_head(Some(git2::build::CheckoutBuilder::default().force()))?;
+            }
+            Err(_) => {
+                repo.reference(
+                    &refname,
+                    fetch_commit.id(),
+                    true,
+                    &format!("Setting {} to {}", remote_branch, fetch_commit.id()),
+                )?;
+                repo.set_head(&refname)?;
+                repo.checkout_head(Some(
+                    git2::build::CheckoutBuilder::default()
+                        .allow_conflicts(true)
+                        .conflict_style_merge(true)
+                        .force(),
+                ))?;
+            }
+        };
+
+        return Ok(true);
+    }
+
+    if analysis.0.is_normal() {
+        let head_commit = repo.reference_to_annotated_commit(&repo.head()?)?;
+        return normal_merge(repo, &head_commit, &fetch_commit);
+    }
+
+    Ok(true)
+}
+
+fn normal_merge(
+    repo: &Repository,
+    local: &git2::AnnotatedCommit,
+    remote: &git2::AnnotatedCommit,
+) -> Result<bool, git2::Error> {
+    let local_tree = repo.find_commit(local.id())?.tree()?;
+    let remote_tree = repo.find_commit(remote.id())?.tree()?;
+    let ancestor = repo
+        .find_commit(repo.merge_base(local.id(), remote.id())?)?
+        .tree()?;
+    let mut idx = repo.merge_trees(&ancestor, &local_tree, &remote_tree, None)?;
+
+    // Leave the tree alone rather than checking out conflict markers
+    if idx.has_conflicts() {
+        return Ok(false);
+    }
+    let result_tree = repo.find_tree(idx.write_tree_to(repo)?)?;
+
+    let msg = format!("Merge: {} into {}", remote.id(), local.id());
+    let sig = repo.signature()?;
+    let local_commit = repo.find_commit(local.id())?;
+    let remote_commit = repo.find_commit(remote.id())?;
+    let _merge_commit = repo.commit(
+        Some("HEAD"),
+        &sig,
+        &sig,
+        &msg,
+        &result_tree,
+        &[&local_commit, &remote_commit],
+    )?;
+
+    repo.checkout_head(None)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use git2::Signature;
+    use tempfile::TempDir;
+
+    use super::*;
+
+    /// Commits a tree with one file to `main` of a bare repo
+    fn commit(repo: &Repository, text: &str) -> Oid {
+        let sig = Signature::now("chalk", "chalk@localhost").unwrap();
+        let blob = repo.blob(text.as_bytes()).unwrap();
+        let mut tree = repo.treebuilder(None).unwrap();
+        tree.insert("file.txt", blob, 0o100644).unwrap();
+        let tree = repo.find_tree(tree.write().unwrap()).unwrap();
+
+        let parent = repo
+            .refname_to_id("refs/heads/main")
+            .ok()
+            .map(|x| repo.find_commit(x).unwrap());
+        let parents = parent.iter().collect::<Vec<_>>();
+        repo.commit(Some("refs/heads/main"), &sig, &sig, text, &tree, &parents)
+            .unwrap()
+    }
+
+    /// A bare repo to fetch from and the path an app's repo would be at
+    fn setup() -> (TempDir, Repository, String) {
+        let dir = TempDir::new().unwrap();
+        let remote = Repository::init_bare(dir.path().join("remote.git")).unwrap();
+        remote.set_head("refs/heads/main").unwrap();
+        let url = dir.path().join("remote.git").to_string_lossy().into_owned();
+        (dir, remote, url)
+    }
+
+    fn config(strategy: &str) -> ProjectGitConfig {
+        toml::from_str(&format!("strategy = \"{strategy}\"")).unwrap()
+    }
+
+    fn branch(name: &str) -> Update<'_> {
+        Update {
+            remote: "origin",
+            fetch: name,
+            branch: name,
+            checkout: None,
+            force: false,
+        }
+    }
+
+    #[test]
+    fn merge_fetches_new_commits() {
+        let (dir, remote, url) = setup();
+        let path = dir.path().join("repo");
+        let config = config("merge");
+
+        let first = commit(&remote, "1");
+        assert!(update(&config, &path, &url, &branch("main")).unwrap());
+        assert_eq!(head_commit(&path), Some(first.to_string()));
+
+        let second = commit(&remote, "2");
+        assert!(update(&config, &path, &url, &branch("main")).unwrap());
+        assert_eq!(head_commit(&path), Some(second.to_string()));
+        assert_eq!(fs::read_to_string(path.join("file.txt")).unwrap(), "2");
+    }
+
+    #[test]
+    fn reset_checks_out_tag() {
+        let (dir, remote, url) = setup();
+        let path = dir.path().join("repo");
+        let config = config("reset");
+
+        let tagged = commit(&remote, "1");
+        let object = remote.find_object(tagged, None).unwrap();
+        remote.tag_lightweight("v1", &object, false).unwrap();
+        commit(&remote, "2");
+
+        let update = Update {
+            fetch: "refs/tags/v1",
+            branch: "v1",
+            ..branch("main")
+        };
+        assert!(super::update(&config, &path, &url, &update).unwrap());
+        assert_eq!(head_commit(&path), Some(tagged.to_string()));
+        assert_eq!(fs::read_to_string(path.join("file.txt")).unwrap(), "1");
+    }
+
+    #[test]
+    fn local_changes_need_force() {
+        let (dir, remote, url) = setup();
+        let path = dir.path().join("repo");
+        let config = config("merge");
+
+        commit(&remote, "1");
+        update(&config, &path, &url, &branch("main")).unwrap();
+        fs::write(path.join("file.txt"), "changed").unwrap();
+        let second = commit(&remote, "2");
+
+        assert!(update(&config, &path, &url, &branch("main")).is_err());
+
+        let force = Update {
+            force: true,
+            ..branch("main")
+        };
+        assert!(update(&config, &path, &url, &force).unwrap());
+        assert_eq!(head_commit(&path), Some(second.to_string()));
+        assert_eq!(fs::read_to_string(path.join("file.txt")).unwrap(), "2");
+    }
+
+    #[test]
+    fn reset_needs_force_for_local_changes() {
+        let (dir, remote, url) = setup();
+        let path = dir.path().join("repo");
+        let config = config("reset");
+
+        commit(&remote, "1");
+        update(&config, &path, &url, &branch("main")).unwrap();
+        fs::write(path.join("file.txt"), "changed").unwrap();
+        let second = commit(&remote, "2");
+
+        let err = update(&config, &path, &url, &branch("main")).unwrap_err();
+        assert!(err.message().contains("Repo has local changes"));
+        assert_eq!(
+            fs::read_to_string(path.join("file.txt")).unwrap(),
+            "changed"
+        );
+
+        let force = Update {
+            force: true,
+            ..branch("main")
+        };
+        assert!(update(&config, &path, &url, &force).unwrap());
+        assert_eq!(head_commit(&path), Some(second.to_string()));
+        assert_eq!(fs::read_to_string(path.join("file.txt")).unwrap(), "2");
+    }
+}
@@ -3,11 +3,14 @@ use std::sync::{atomic::Ordering, Arc};
 
 mod api;
 mod app;
+mod check;
 mod config;
-mod ctrlc;
+mod git;
 mod misc;
 mod project;
-use app::{App, LogType};
+mod signals;
+mod watcher;
+use app::{App, LogLevel, LogSource};
 use misc::Timer;
 use project::Project;
 
@@ -15,30 +18,37 @@ const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 fn main() {
     let app = Arc::new(App::new());
-    app.log(LogType::Info, format!("Starting (v{VERSION})"));
+    app.log(LogLevel::Info, format!("Starting (v{VERSION})"));
 
     // Init SIG(INT|TERM|HUP) handler
-    ctrlc::init(app.clone());
+    signals::init(app.clone());
 
     // Load Projects
-    app.projects
-        .write()
-        .extend(Project::find_projects(app.clone()));
+    Project::rescan(app.clone());
 
     // Start projects
-    app.projects.read().iter().for_each(Project::start);
+    // Errors are logged by `start`
+    app.projects.read().iter().for_each(|x| {
+        let _ = x.start();
+    });
+
+    // Watch for app changes
+    watcher::start(app.clone());
 
     // Start API
     api::start(app.clone());
 
     // Start an loop to poll tasks and manage logs
-    Timer::new(app.config.task_poll).start(|| {
+    let task_poll = app.config.read().task_poll;
+    Timer::new(task_poll).start(|timer| {
         app.projects
             .read()
             .iter()
             .filter(|x| x.status.read().is_running())
-            .for_each(Project::poll);
+            .for_each(|x| x.poll());
+        Project::apply_staged(app.clone());
 
+        timer.time = app.config.read().task_poll;
         app.log_tick(false);
         if app.last_exit_try.load(Ordering::Relaxed) != 0 && Project::any_running(app.clone()) {
             app.log_tick(true);
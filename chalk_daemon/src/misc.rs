@@ -1,15 +1,20 @@
 use std::borrow::Cow;
-use std::fmt::Display;
-use std::net::IpAddr;
+use std::io::{self, Read, Write};
+use std::net::{IpAddr, TcpStream, ToSocketAddrs};
+use std::path::{Component, Path};
+use std::process::Child;
 use std::sync::Arc;
 use std::thread;
 use std::time::{Duration, Instant};
 
 use afire::{Content, Request, Response};
-use git2::Repository;
+use chalk_client::models::TokenType;
+use nix::sys::signal::{self, Signal};
+use nix::unistd::Pid;
+use serde::Serialize;
 use serde_json::json;
 
-use crate::app::{App, LogType};
+use crate::app::{App, LogLevel, LogSource};
 
 // == Timer ==
 
@@ -29,10 +34,11 @@ impl Timer {
         }
     }
 
-    pub fn start(&mut self, fun: impl Fn()) {
+    /// The function gets the timer so it can change the loop time
+    pub fn start(&mut self, fun: impl Fn(&mut Self)) {
         loop {
             self.start = Instant::now();
-            fun();
+            fun(self);
 
             thread::sleep(Duration::from_millis(
                 self.time
@@ -57,7 +63,7 @@ pub enum ValidateType {
 
 impl ValidateType {
     pub fn token_type(app: Arc<App>, token: String) -> Self {
-        if token == app.config.api.token {
+        if token == app.config.read().api.token {
             return ValidateType::Global;
         }
 
@@ -74,7 +80,7 @@ impl ValidateType {
     }
 
     pub fn validate(&self, app: Arc<App>, token: String) -> bool {
-        token == app.config.api.token
+        token == app.config.read().api.token
             || match self {
                 ValidateType::Global => false,
                 ValidateType::Scoped(project) => {
@@ -92,25 +98,35 @@ impl ValidateType {
                     .any(|x| x.config.api_token == token),
             }
     }
+
+    /// Who made a release, for its info (EX: `app token from 127.0.0.1`)
+    pub fn uploader(app: Arc<App>, token: String, ip: IpAddr) -> String {
+        let kind = match Self::token_type(app, token) {
+            ValidateType::Global => "global token",
+            _ => "app token",
+        };
+        format!("{kind} from {ip}")
+    }
 }
 
-impl Display for ValidateType {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(match self {
-            ValidateType::Any => "any",
-            ValidateType::Global => "global",
-            ValidateType::Scoped(..) => "scoped",
-        })
+impl From<ValidateType> for TokenType {
+    fn from(from: ValidateType) -> Self {
+        match from {
+            ValidateType::Any => TokenType::Any,
+            ValidateType::Global => TokenType::Global,
+            ValidateType::Scoped(..) => TokenType::Scoped,
+        }
     }
 }
 
 // == Misc Functions ==
 
 pub fn token_error(app: Arc<App>, req: &Request, token: String) -> Response {
-    app.log(
-        LogType::Info,
+    app.log_from(
+        LogSource::Web,
+        LogLevel::Warn,
         format!(
-            "[WEB] [{}] Tried Invalid token `{}` on `{}`",
+            "[{}] Tried Invalid token `{}` on `{}`",
             req.real_ip(),
             token,
             req.path
@@ -119,6 +135,14 @@ pub fn token_error(app: Arc<App>, req: &Request, token: String) -> Response {
     error_res("Invalid Token")
 }
 
+/// Gets a header without caring about its case
+pub fn header<'a>(req: &'a Request, name: &str) -> Option<&'a str> {
+    req.headers
+        .iter()
+        .find(|x| x.name.to_string().eq_ignore_ascii_case(name))
+        .map(|x| x.value.as_str())
+}
+
 pub fn error_res<T: AsRef<str>>(err: T) -> Response {
     Response::new()
         .status(400)
@@ -126,6 +150,12 @@ pub fn error_res<T: AsRef<str>>(err: T) -> Response {
         .content(Content::JSON)
 }
 
+pub fn json_res<T: Serialize>(data: &T) -> Response {
+    Response::new()
+        .text(serde_json::to_string(data).unwrap())
+        .content(Content::JSON)
+}
+
 // == Traits ==
 
 pub trait RealIp {
@@ -133,7 +163,7 @@ pub trait RealIp {
 }
 
 pub trait BodyString {
-    fn body_string(&self) -> Cow<str>;
+    fn body_string(&self) -> Cow<'_, str>;
 }
 
 impl RealIp for Request {
@@ -156,99 +186,123 @@ impl RealIp for Request {
 }
 
 impl BodyString for Request {
-    fn body_string(&self) -> Cow<str> {
+    fn body_string(&self) -> Cow<'_, str> {
         String::from_utf8_lossy(&self.body)
     }
 }
 
-// == Git stuff ==
-// Modified from https://github.com/rust-lang/git2-rs examples
-
-pub fn do_merge<'a>(
-    repo: &'a Repository,
-    remote_branch: &str,
-    fetch_commit: git2::AnnotatedCommit<'a>,
-) -> Result<bool, git2::Error> {
-    let analysis = repo.merge_analysis(&[&fetch_commit])?;
-
-    if analysis.0.is_fast_forward() {
-        let refname = format!("refs/heads/{remote_branch}");
-        match repo.find_reference(&refname) {
-            Ok(mut r) => {
-                let name = match r.name() {
-                    Some(s) => s.to_string(),
-                    None => String::from_utf8_lossy(r.name_bytes()).to_string(),
-                };
-                r.set_target(
-                    fetch_commit.id(),
-                    &format!(
-                        "Fast-Forward: Setting {} to id: {}",
-                        name,
-                        fetch_commit.id()
-                    ),
-                )?;
-                repo.set_head(&name)?;
-                repo.checkout_head(Some(git2::build::CheckoutBuilder::default().force()))?;
-            }
-            Err(_) => {
-                repo.reference(
-                    &refname,
-                    fetch_commit.id(),
-                    true,
-                    &format!("Setting {} to {}", remote_branch, fetch_commit.id()),
-                )?;
-                repo.set_head(&refname)?;
-                repo.checkout_head(Some(
-                    git2::build::CheckoutBuilder::default()
-                        .allow_conflicts(true)
-                        .conflict_style_merge(true)
-                        .force(),
-                ))?;
-            }
-        };
-
-        return Ok(true);
+// == Matching ==
+
+/// Matches text against a pattern where `*` matches any (possibly empty) run of characters
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let mut rest = match text.strip_prefix(first) {
+        Some(i) => i,
+        None => return false,
+    };
+
+    let parts = parts.collect::<Vec<_>>();
+    let (last, middle) = match parts.split_last() {
+        Some(i) => i,
+        // No wildcards
+        None => return rest.is_empty(),
+    };
+
+    for i in middle {
+        match rest.find(i) {
+            Some(j) => rest = &rest[j + i.len()..],
+            None => return false,
+        }
     }
 
-    if analysis.0.is_normal() {
-        let head_commit = repo.reference_to_annotated_commit(&repo.head()?)?;
-        return normal_merge(repo, &head_commit, &fetch_commit);
+    rest.ends_with(last)
+}
+
+/// Compares two byte strings in time that only depends on their lengths
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
     }
 
-    Ok(true)
+    a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
 }
 
-fn normal_merge(
-    repo: &Repository,
-    local: &git2::AnnotatedCommit,
-    remote: &git2::AnnotatedCommit,
-) -> Result<bool, git2::Error> {
-    let local_tree = repo.find_commit(local.id())?.tree()?;
-    let remote_tree = repo.find_commit(remote.id())?.tree()?;
-    let ancestor = repo
-        .find_commit(repo.merge_base(local.id(), remote.id())?)?
-        .tree()?;
-    let mut idx = repo.merge_trees(&ancestor, &local_tree, &remote_tree, None)?;
+/// Sends a plain http request and returns the status code and body of the response.
+/// `timeout` is used for connecting and for each read / write.
+pub fn http_request(
+    method: &str,
+    url: &str,
+    headers: &[(&str, &str)],
+    body: &[u8],
+    timeout: Duration,
+) -> io::Result<(u16, Vec<u8>)> {
+    let invalid = |e: &str| io::Error::new(io::ErrorKind::InvalidInput, e);
+    let rest = url
+        .strip_prefix("http://")
+        .ok_or_else(|| invalid("Only http urls are supported"))?;
+    let (host, path) = match rest.split_once('/') {
+        Some((host, path)) => (host, format!("/{path}")),
+        None => (rest, "/".to_owned()),
+    };
+    let addr = match host.contains(':') {
+        true => host.to_owned(),
+        false => format!("{host}:80"),
+    };
+    let addr = addr
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| invalid("Host not found"))?;
+
+    let mut stream = TcpStream::connect_timeout(&addr, timeout)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+
+    let mut head = format!("{method} {path} HTTP/1.0\r\nHost: {host}\r\nConnection: close\r\n");
+    for (name, value) in headers {
+        head.push_str(&format!("{name}: {value}\r\n"));
+    }
+    head.push_str(&format!("Content-Length: {}\r\n\r\n", body.len()));
+    stream.write_all(head.as_bytes())?;
+    stream.write_all(body)?;
+
+    // The connection is closed after the response
+    let mut res = Vec::new();
+    stream.read_to_end(&mut res)?;
+
+    let bad = || io::Error::new(io::ErrorKind::InvalidData, "Invalid http response");
+    let head_end = res
+        .windows(4)
+        .position(|x| x == b"\r\n\r\n")
+        .ok_or_else(bad)?;
+    // HTTP/1.1 200 OK
+    let status = String::from_utf8_lossy(&res[..head_end])
+        .split_whitespace()
+        .nth(1)
+        .and_then(|x| x.parse().ok())
+        .ok_or_else(bad)?;
+
+    Ok((status, res[head_end + 4..].to_vec()))
+}
+
+/// Kills a child started in its own process group (`CommandExt::process_group(0)`) and anything it started, then waits for it.
+/// Killing just the child would leave the commands a `sh -c` started running.
+pub fn kill_group(child: &mut Child) {
+    let _ = signal::killpg(Pid::from_raw(child.id() as i32), Signal::SIGKILL);
+    let _ = child.wait();
+}
 
-    if idx.has_conflicts() {
-        repo.checkout_index(Some(&mut idx), None)?;
-        return Ok(false);
+/// Checks that a relative path does not leave its parent folder
+pub fn inside(path: &Path) -> bool {
+    let mut depth = 0;
+    for i in path.components() {
+        match i {
+            Component::Normal(_) => depth += 1,
+            Component::CurDir => {}
+            Component::ParentDir if depth > 0 => depth -= 1,
+            _ => return false,
+        }
     }
-    let result_tree = repo.find_tree(idx.write_tree_to(repo)?)?;
-
-    let msg = format!("Merge: {} into {}", remote.id(), local.id());
-    let sig = repo.signature()?;
-    let local_commit = repo.find_commit(local.id())?;
-    let remote_commit = repo.find_commit(remote.id())?;
-    let _merge_commit = repo.commit(
-        Some("HEAD"),
-        &sig,
-        &sig,
-        &msg,
-        &result_tree,
-        &[&local_commit, &remote_commit],
-    )?;
-
-    repo.checkout_head(None)?;
-    Ok(true)
+
+    true
 }
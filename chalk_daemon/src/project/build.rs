@@ -0,0 +1,106 @@
+use std::fs::{self, File};
+use std::io::Write;
+use std::os::unix::process::CommandExt;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+
+use super::{config::ProjectBuildConfig, Project};
+use crate::{git, misc, LogLevel};
+
+impl Project {
+    /// Runs the app's build command in its repo and makes a release from the artifact if it works.
+    /// Output goes to `[project_path]/build.log`.
+    /// Does nothing if the app has no `[build]` config.
+    pub fn build(&self, uploader: &str) -> Result<(), String> {
+        let config = match &self.config.build {
+            Some(i) => i,
+            None => return Ok(()),
+        };
+
+        self.log(LogLevel::Info, format!("Building `{}`", self.name));
+        let start = Instant::now();
+        if let Err(e) = self.run_build(config, uploader) {
+            self.log(
+                LogLevel::Error,
+                format!("Build of `{}` failed: {e}", self.name),
+            );
+            return Err(e);
+        }
+
+        self.log(
+            LogLevel::Info,
+            format!(
+                "Built `{}` in {:.1}s",
+                self.name,
+                start.elapsed().as_secs_f32()
+            ),
+        );
+        Ok(())
+    }
+
+    fn run_build(&self, config: &ProjectBuildConfig, uploader: &str) -> Result<(), String> {
+        let repo_path = self.project_path.join("repo");
+        let mut log = File::create(self.project_path.join("build.log"))
+            .map_err(|e| format!("Error creating build log: {e}"))?;
+        let _ = writeln!(log, "[{}] $ {}", Utc::now().to_rfc3339(), config.command);
+
+        let (stdout, stderr) = match (log.try_clone(), log.try_clone()) {
+            (Ok(i), Ok(j)) => (i, j),
+            (Err(e), _) | (_, Err(e)) => return Err(format!("Error opening build log: {e}")),
+        };
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(&config.command)
+            .current_dir(repo_path.join(&config.path))
+            .envs(&config.environment_vars)
+            // So a timeout can kill everything the build started (EX: `cargo` and `rustc` under `sh`)
+            .process_group(0)
+            .stdin(Stdio::null())
+            .stdout(stdout)
+            .stderr(stderr)
+            .spawn()
+            .map_err(|e| format!("Error starting build: {e}"))?;
+
+        let timeout = Duration::from_secs(config.timeout);
+        let start = Instant::now();
+        let status = loop {
+            match child.try_wait() {
+                Ok(Some(i)) => break i,
+                Ok(None) => {}
+                Err(e) => {
+                    misc::kill_group(&mut child);
+                    return Err(format!("Error waiting on build: {e}"));
+                }
+            }
+
+            if start.elapsed() >= timeout {
+                misc::kill_group(&mut child);
+                let _ = writeln!(log, "[*] Killed after {}s", config.timeout);
+                return Err(format!("Timed out after {}s", config.timeout));
+            }
+
+            thread::sleep(Duration::from_millis(100));
+        };
+
+        let _ = writeln!(log, "[*] Build {status}");
+        if !status.success() {
+            return Err(format!("Build {status}, see build.log"));
+        }
+
+        let artifact = fs::read(repo_path.join(&config.artifact))
+            .map_err(|e| format!("Error reading `{}`: {e}", config.artifact))?;
+        // A detached signature can be left next to the artifact (EX: fetched from CI)
+        let signature = fs::read_to_string(repo_path.join(format!("{}.sig", config.artifact))).ok();
+        self.add_release(
+            &artifact,
+            signature.as_deref(),
+            git::head_commit(&repo_path),
+            uploader,
+        )?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,335 @@
+use std::fs::{self, File, Permissions};
+use std::io::{self, Read, Seek};
+use std::os::unix::fs::{symlink, PermissionsExt};
+use std::path::{Component, Path, PathBuf};
+
+use chalk_client::models::BundleKind;
+use flate2::read::GzDecoder;
+use tar::{Archive, EntryType};
+use zip::ZipArchive;
+
+enum Entry {
+    Dir,
+    File(u32),
+    Symlink(PathBuf),
+}
+
+/// Longest symlink target read from a zip
+const MAX_LINK: u64 = 4096;
+
+/// Unpacks an archive into `dest`, which should be a new empty folder.
+/// Entries that would end up outside of `dest` (absolute paths, `..`, symlinks with `..` or links written through) are rejected.
+/// Fails once more than `limit` bytes were unpacked, so a small archive can not fill the disk.
+pub fn extract(
+    kind: BundleKind,
+    data: impl Read + Seek,
+    dest: &Path,
+    limit: u64,
+) -> Result<(), String> {
+    let mut left = limit;
+    match kind {
+        BundleKind::TarGz => extract_tar(data, dest, &mut left),
+        BundleKind::Zip => extract_zip(data, dest, &mut left),
+    }
+}
+
+fn extract_tar(data: impl Read, dest: &Path, left: &mut u64) -> Result<(), String> {
+    let mut archive = Archive::new(GzDecoder::new(data));
+    let entries = archive
+        .entries()
+        .map_err(|e| format!("Error reading archive: {e}"))?;
+
+    for i in entries {
+        let mut i = i.map_err(|e| format!("Error reading archive: {e}"))?;
+        let path = i
+            .path()
+            .map_err(|e| format!("Error reading archive: {e}"))?
+            .into_owned();
+
+        let entry = match i.header().entry_type() {
+            EntryType::Directory => Entry::Dir,
+            EntryType::Regular | EntryType::Continuous => {
+                Entry::File(i.header().mode().unwrap_or(0o644))
+            }
+            EntryType::Symlink => match i.link_name() {
+                Ok(Some(i)) => Entry::Symlink(i.into_owned()),
+                _ => return Err(format!("Symlink `{}` has no target", path.display())),
+            },
+            // Only metadata
+            EntryType::XGlobalHeader | EntryType::XHeader => continue,
+            _ => {
+                return Err(format!(
+                    "Unsupported entry `{}`, only files, folders and symlinks are allowed",
+                    path.display()
+                ))
+            }
+        };
+
+        write_entry(dest, &path, entry, &mut i, left)?;
+    }
+
+    Ok(())
+}
+
+fn extract_zip(data: impl Read + Seek, dest: &Path, left: &mut u64) -> Result<(), String> {
+    let mut archive = ZipArchive::new(data).map_err(|e| format!("Error reading archive: {e}"))?;
+
+    for i in 0..archive.len() {
+        let mut file = archive
+            .by_index(i)
+            .map_err(|e| format!("Error reading archive: {e}"))?;
+        let path = PathBuf::from(file.name());
+        let mode = file.unix_mode();
+
+        let entry = if file.is_dir() {
+            Entry::Dir
+        } else if mode.map(|x| x & 0o170000 == 0o120000).unwrap_or(false) {
+            // Zip stores symlink targets as the file contents
+            let mut target = String::new();
+            (&mut file)
+                .take(MAX_LINK)
+                .read_to_string(&mut target)
+                .map_err(|e| format!("Error reading archive: {e}"))?;
+            Entry::Symlink(PathBuf::from(target))
+        } else {
+            Entry::File(mode.unwrap_or(0o644))
+        };
+
+        write_entry(dest, &path, entry, &mut file, left)?;
+    }
+
+    Ok(())
+}
+
+fn write_entry(
+    dest: &Path,
+    path: &Path,
+    entry: Entry,
+    data: &mut impl Read,
+    left: &mut u64,
+) -> Result<(), String> {
+    let out = safe_path(dest, path)?;
+    let error = |e: io::Error| format!("Error extracting `{}`: {e}", path.display());
+
+    match entry {
+        Entry::Dir => fs::create_dir_all(&out).map_err(error)?,
+        Entry::File(mode) => {
+            if let Some(i) = out.parent() {
+                fs::create_dir_all(i).map_err(error)?;
+            }
+            // Never replace an earlier symlink, writing to it would follow it
+            if out.symlink_metadata().is_ok() {
+                return Err(format!("Duplicate entry `{}`", path.display()));
+            }
+
+            let mut file = File::create(&out).map_err(error)?;
+            // One byte over the limit is enough to know it is too big
+            let written = io::copy(&mut data.take(*left + 1), &mut file).map_err(error)?;
+            if written > *left {
+                return Err("Bundle unpacks to more than `api.max_unpacked`".to_owned());
+            }
+            *left -= written;
+            // No setuid / setgid / sticky bits
+            fs::set_permissions(&out, Permissions::from_mode(mode & 0o777)).map_err(error)?;
+        }
+        Entry::Symlink(target) => {
+            // Only checking the path text would let a `..` go through another symlink (EX: `x/z -> y/..` with `x/y -> ..`),
+            // so targets can only point down from the link
+            let down = target
+                .components()
+                .all(|x| matches!(x, Component::Normal(_) | Component::CurDir));
+            if !down {
+                return Err(format!(
+                    "Symlink `{}` can only point into its own folder, without `..`",
+                    path.display()
+                ));
+            }
+
+            if let Some(i) = out.parent() {
+                fs::create_dir_all(i).map_err(error)?;
+            }
+            symlink(&target, &out).map_err(error)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Joins an entry path onto `dest`, making sure it stays inside and does not go through a symlink
+fn safe_path(dest: &Path, path: &Path) -> Result<PathBuf, String> {
+    let mut out = dest.to_path_buf();
+    for i in path.components() {
+        match i {
+            Component::Normal(i) => {
+                if out
+                    .symlink_metadata()
+                    .map(|x| x.file_type().is_symlink())
+                    .unwrap_or(false)
+                {
+                    return Err(format!("Entry `{}` goes through a symlink", path.display()));
+                }
+                out.push(i);
+            }
+            Component::CurDir => {}
+            _ => {
+                return Err(format!(
+                    "Entry `{}` points outside of the bundle",
+                    path.display()
+                ))
+            }
+        }
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use flate2::{write::GzEncoder, Compression};
+    use tar::{Builder, Header};
+    use tempfile::TempDir;
+    use zip::{write::FileOptions, ZipWriter};
+
+    use super::*;
+
+    enum Item<'a> {
+        File(&'a str, &'a [u8]),
+        Link(&'a str, &'a str),
+    }
+
+    /// Builds a tar.gz, writing the names as is so `..` and absolute paths can be put in
+    fn tar_gz(items: &[Item]) -> Vec<u8> {
+        let mut builder = Builder::new(GzEncoder::new(Vec::new(), Compression::fast()));
+        for i in items {
+            let mut header = Header::new_gnu();
+            let (name, data) = match i {
+                Item::File(name, data) => {
+                    header.set_entry_type(EntryType::Regular);
+                    header.set_size(data.len() as u64);
+                    (name, *data)
+                }
+                Item::Link(name, target) => {
+                    header.set_entry_type(EntryType::Symlink);
+                    header.set_link_name(target).unwrap();
+                    header.set_size(0);
+                    (name, &[][..])
+                }
+            };
+            header.as_gnu_mut().unwrap().name[..name.len()].copy_from_slice(name.as_bytes());
+            header.set_mode(0o755);
+            header.set_cksum();
+            builder.append(&header, data).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    fn zip(items: &[Item]) -> Vec<u8> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        for i in items {
+            match i {
+                Item::File(name, data) => {
+                    zip.start_file(*name, FileOptions::default()).unwrap();
+                    zip.write_all(data).unwrap();
+                }
+                Item::Link(name, target) => {
+                    zip.add_symlink(*name, *target, FileOptions::default())
+                        .unwrap();
+                }
+            }
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    /// Extracts both kinds of archive with the same entries, each into its own folder
+    fn extract_both(items: &[Item], limit: u64) -> (TempDir, [Result<(), String>; 2]) {
+        let dir = TempDir::new().unwrap();
+        let tar = dir.path().join("tar");
+        let zip_dest = dir.path().join("zip");
+        fs::create_dir(&tar).unwrap();
+        fs::create_dir(&zip_dest).unwrap();
+
+        let res = [
+            extract(BundleKind::TarGz, Cursor::new(tar_gz(items)), &tar, limit),
+            extract(BundleKind::Zip, Cursor::new(zip(items)), &zip_dest, limit),
+        ];
+        (dir, res)
+    }
+
+    #[test]
+    fn extracts_files_and_links() {
+        let items = [
+            Item::File("app/bin", b"binary"),
+            Item::Link("app/current", "bin"),
+            Item::Link("static", "app/./bin"),
+        ];
+        let (dir, res) = extract_both(&items, 1024);
+
+        for (kind, res) in ["tar", "zip"].iter().zip(res) {
+            res.unwrap();
+            let dest = dir.path().join(kind);
+            assert_eq!(fs::read(dest.join("app/bin")).unwrap(), b"binary");
+            assert_eq!(fs::read(dest.join("app/current")).unwrap(), b"binary");
+            assert_eq!(fs::read(dest.join("static")).unwrap(), b"binary");
+        }
+    }
+
+    #[test]
+    fn rejects_parent_entries() {
+        let (dir, res) = extract_both(&[Item::File("a/../../escaped", b"x")], 1024);
+        for res in res {
+            assert!(res.unwrap_err().contains("outside of the bundle"));
+        }
+        assert!(!dir.path().join("escaped").exists());
+    }
+
+    #[test]
+    fn rejects_absolute_entries() {
+        let dir = TempDir::new().unwrap();
+        let target = dir.path().join("absolute");
+        let name = target.to_string_lossy();
+
+        let (_dest, res) = extract_both(&[Item::File(&name, b"x")], 1024);
+        for res in res {
+            assert!(res.unwrap_err().contains("outside of the bundle"));
+        }
+        assert!(!target.exists());
+    }
+
+    #[test]
+    fn rejects_upward_symlinks() {
+        for target in ["..", "../../etc", "a/../..", "/etc"] {
+            let (_dir, res) = extract_both(&[Item::Link("link", target)], 1024);
+            for res in res {
+                assert!(res
+                    .unwrap_err()
+                    .contains("can only point into its own folder"));
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_writing_through_symlinks() {
+        let items = [Item::Link("link", "folder"), Item::File("link/file", b"x")];
+        let (_dir, res) = extract_both(&items, 1024);
+        for res in res {
+            assert!(res.unwrap_err().contains("goes through a symlink"));
+        }
+    }
+
+    #[test]
+    fn limits_unpacked_size() {
+        let items = [Item::File("a", &[0; 600]), Item::File("b", &[0; 600])];
+
+        let (_dir, res) = extract_both(&items, 1200);
+        for res in res {
+            res.unwrap();
+        }
+
+        let (_dir, res) = extract_both(&items, 1199);
+        for res in res {
+            assert!(res.unwrap_err().contains("max_unpacked"));
+        }
+    }
+}
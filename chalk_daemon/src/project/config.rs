@@ -1,7 +1,7 @@
 use serde_derive::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub struct ProjectConfig {
     // Misc
     pub name: String,
@@ -9,9 +9,20 @@ pub struct ProjectConfig {
 
     pub run: ProjectRunConfig,
     pub git: ProjectGitConfig,
+    pub build: Option<ProjectBuildConfig>,
+    #[serde(default)]
+    pub releases: ProjectReleaseConfig,
+    #[serde(default)]
+    pub deploy: ProjectDeployConfig,
+    pub webhook: Option<ProjectWebhookConfig>,
+    #[serde(default)]
+    pub hooks: ProjectHooksConfig,
+    pub control: Option<ProjectControlConfig>,
+    #[serde(default)]
+    pub notify: ProjectNotifyConfig,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub struct ProjectRunConfig {
     pub path: String,
     pub command: String,
@@ -19,10 +30,183 @@ pub struct ProjectRunConfig {
     pub environment_vars: HashMap<String, String>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub struct ProjectGitConfig {
     pub repo: Option<String>,
     pub username: Option<String>,
     pub token: Option<String>,
     pub ssh_key_file: Option<String>,
+    /// How fetched commits are applied to the repo
+    #[serde(default)]
+    pub strategy: GitStrategy,
+    /// Only fetch this many commits of history
+    pub depth: Option<u32>,
+    /// Initialise and update submodules after each update
+    #[serde(default)]
+    pub submodules: bool,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GitStrategy {
+    /// Merge the fetched commit into the local branch
+    #[default]
+    Merge,
+    /// Hard reset to exactly the fetched commit, never making commits.
+    /// Like merging, a tree with local changes is only reset with `force`.
+    Reset,
+}
+
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ProjectBuildConfig {
+    /// Shell command to run (EX: cargo build --release)
+    pub command: String,
+    /// Working dir, relative to the repo
+    #[serde(default = "default_path")]
+    pub path: String,
+    #[serde(default)]
+    pub environment_vars: HashMap<String, String>,
+    /// Seconds before the build is killed
+    #[serde(default = "default_timeout")]
+    pub timeout: u64,
+    /// Built file, relative to the repo. Copied to `run.command` if the build works.
+    pub artifact: String,
+}
+
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ProjectReleaseConfig {
+    /// Number of releases to keep for rollbacks
+    pub keep: usize,
+    /// Paths in bundles that are kept across deploys (EX: `data/`).
+    /// They are linked to `[project_path]/shared` in every release.
+    pub preserve: Vec<String>,
+    /// Hex encoded ed25519 public keys.
+    /// If any are set every release needs a detached signature from one of them.
+    pub trusted_keys: Vec<String>,
+}
+
+/// How a running app is restarted after a deploy
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ProjectDeployConfig {
+    pub strategy: DeployStrategy,
+    /// Port the new instance has to listen on before it is switched to
+    pub ready_port: Option<u16>,
+    /// Url that has to return a 2xx before the new instance is switched to.
+    /// If both instances share a port the old one can answer it, so pair it with `ready_port`.
+    pub ready_url: Option<String>,
+    /// Seconds to wait for the new instance to be ready
+    pub ready_timeout: u64,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeployStrategy {
+    /// Stop the app, then start it again
+    #[default]
+    Restart,
+    /// Start the new release next to the old one and stop the old one once the new one is ready.
+    /// The app has to be able to run twice at once (EX: with `SO_REUSEPORT`).
+    BlueGreen,
+}
+
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ProjectWebhookConfig {
+    /// Secret used to sign (GitHub, Gitea) or send (GitLab) webhooks
+    pub secret: String,
+    /// Git remote to fetch from
+    #[serde(default = "default_remote")]
+    pub remote: String,
+    /// Branches to deploy, `*` matches anything (EX: release/*)
+    pub branch: Option<String>,
+    /// Tags to deploy, `*` matches anything (EX: v*)
+    pub tag: Option<String>,
+}
+
+/// Shell commands run around the app's lifecycle, in `run.path` with the app's environment.
+/// A failing `pre_*` hook aborts what it was run for, `post_*` hooks run in the background.
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ProjectHooksConfig {
+    pub pre_start: Option<String>,
+    pub post_start: Option<String>,
+    pub pre_stop: Option<String>,
+    pub post_stop: Option<String>,
+    pub pre_deploy: Option<String>,
+    pub post_deploy: Option<String>,
+    /// Seconds before a hook is killed
+    pub timeout: u64,
+}
+
+/// Where the app serves its `afire_integration::RemoteControl` route
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ProjectControlConfig {
+    /// Full url of the route (EX: http://localhost:8080/control)
+    pub url: String,
+    /// Seconds to wait for the app to respond
+    #[serde(default = "default_control_timeout")]
+    pub timeout: u64,
+}
+
+/// Settings for the sd_notify socket passed to the app as `NOTIFY_SOCKET`
+#[derive(Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ProjectNotifyConfig {
+    /// The app only counts as running once it sends `READY=1` (like systemd's `Type=notify`)
+    pub ready: bool,
+    /// Seconds the app can go without sending `WATCHDOG=1` before it is restarted.
+    /// Passed to the app as `WATCHDOG_USEC`.
+    pub watchdog: Option<u64>,
+}
+
+impl Default for ProjectReleaseConfig {
+    fn default() -> Self {
+        Self {
+            keep: 5,
+            preserve: Vec::new(),
+            trusted_keys: Vec::new(),
+        }
+    }
+}
+
+impl Default for ProjectDeployConfig {
+    fn default() -> Self {
+        Self {
+            strategy: DeployStrategy::Restart,
+            ready_port: None,
+            ready_url: None,
+            ready_timeout: 30,
+        }
+    }
+}
+
+impl Default for ProjectHooksConfig {
+    fn default() -> Self {
+        Self {
+            pre_start: None,
+            post_start: None,
+            pre_stop: None,
+            post_stop: None,
+            pre_deploy: None,
+            post_deploy: None,
+            timeout: 60,
+        }
+    }
+}
+
+fn default_path() -> String {
+    ".".to_owned()
+}
+
+fn default_timeout() -> u64 {
+    600
+}
+
+fn default_control_timeout() -> u64 {
+    10
+}
+
+fn default_remote() -> String {
+    "origin".to_owned()
 }
@@ -0,0 +1,42 @@
+use std::time::Duration;
+
+use chalk_client::control::{self, Direction};
+use serde_json::{json, Value};
+
+use super::Project;
+use crate::misc;
+
+impl Project {
+    /// Runs a `system` of the app's `RemoteControl`, signed with the app's interface secret.
+    /// Returns the status code and body the app responded with.
+    pub fn control(&self, action: &str, data: Value) -> Result<(u16, String), String> {
+        let config = match &self.config.control {
+            Some(i) => i,
+            None => return Err("App has no control url".to_owned()),
+        };
+        if !self.status.read().is_running() {
+            return Err("App is not running".to_owned());
+        }
+
+        // Copied so no lock is held during the request
+        let secret = self.process.secret.read().to_owned();
+        let body = json!({ "action": action, "data": data }).to_string();
+        let signed = control::sign(&secret, Direction::Control, body.as_bytes());
+        let headers = [
+            (control::TIMESTAMP_HEADER, signed.timestamp.as_str()),
+            (control::NONCE_HEADER, signed.nonce.as_str()),
+            (control::SIGNATURE_HEADER, signed.signature.as_str()),
+        ];
+
+        let (status, body) = misc::http_request(
+            "POST",
+            &config.url,
+            &headers,
+            body.as_bytes(),
+            Duration::from_secs(config.timeout),
+        )
+        .map_err(|e| format!("Error sending control request: {e}"))?;
+
+        Ok((status, String::from_utf8_lossy(&body).into_owned()))
+    }
+}
@@ -0,0 +1,203 @@
+use std::fs;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use nix::sys::signal::Signal;
+
+use super::{config::DeployStrategy, Hook, Process, Project, ProjectStatus, STOP_TIMEOUT};
+use crate::{misc, LogLevel};
+
+impl Project {
+    /// Restarts a running app with its deploy strategy.
+    /// If a blue/green switch fails the old instance is kept and the error is returned.
+    pub fn restart(&self) -> Result<(), String> {
+        if self.config.deploy.strategy == DeployStrategy::BlueGreen
+            && self.status.read().is_running()
+        {
+            if let Err(e) = self.blue_green() {
+                self.log(
+                    LogLevel::Error,
+                    format!(
+                        "Switching `{}` to a new instance failed: {e}. Keeping the old one.",
+                        self.name
+                    ),
+                );
+                return Err(e);
+            }
+            return Ok(());
+        }
+
+        self.stop_wait(Signal::SIGINT, STOP_TIMEOUT)?;
+        self.start()
+    }
+
+    /// Restarts a running app after `current` was switched.
+    /// If the new instance fails, `current` goes back to `previous`.
+    pub fn restart_or_revert(&self, previous: Option<u32>) -> Result<(), String> {
+        let res = self.restart();
+        if res.is_err() {
+            if let Some(i) = previous {
+                let _ = self.switch_release(i);
+            }
+        }
+        res
+    }
+
+    /// Starts a second instance, waits for it to be ready, then stops the old one.
+    /// The old instance keeps its secret until the switch is done.
+    fn blue_green(&self) -> Result<(), String> {
+        self.run_hook(Hook::PreStart)?;
+        let instance = self.spawn();
+        let instance = match instance {
+            Ok(i) => i,
+            Err(e) => {
+                self.abort_switch();
+                return Err(format!("Error starting new instance: {e}"));
+            }
+        };
+        let pid = instance.child.id();
+
+        let next = Process::new();
+        next.attach(instance);
+        *self.next.lock() = Some(next);
+        self.log(
+            LogLevel::Info,
+            format!("Started new instance of `{}` ({pid})", self.name),
+        );
+
+        if let Err(e) = self.wait_ready(pid) {
+            self.abort_switch();
+            return Err(e);
+        }
+
+        self.log(
+            LogLevel::Info,
+            format!("Stopping old instance of `{}`", self.name),
+        );
+        self.process.signal(Signal::SIGINT);
+        self.wait_exit(STOP_TIMEOUT);
+
+        // Taken on its own, `next` is always locked before `process`
+        let next = self.next.lock().take();
+        if let Some(i) = next {
+            self.process.replace(i);
+        }
+        *self.status.write() = ProjectStatus::Running;
+        self.log(
+            LogLevel::Info,
+            format!("Switched `{}` to its new instance", self.name),
+        );
+        self.spawn_hook(Hook::PostStart);
+        Ok(())
+    }
+
+    /// Waits for the new instance to be ready, or for it to exit or time out
+    fn wait_ready(&self, pid: u32) -> Result<(), String> {
+        let ready_timeout = self.config.deploy.ready_timeout;
+        let timeout = Duration::from_secs(ready_timeout);
+        let start = Instant::now();
+        loop {
+            if let Some(i) = self.next_exited() {
+                return Err(format!("New instance exited with {i}"));
+            }
+
+            if self.ready(pid) {
+                return Ok(());
+            }
+
+            if start.elapsed() >= timeout {
+                return Err(format!("Not ready after {ready_timeout}s"));
+            }
+
+            thread::sleep(Duration::from_millis(250));
+        }
+    }
+
+    /// Kills the new instance of a failed switch and gives the key file back to the old one
+    fn abort_switch(&self) {
+        if let Some(i) = self.next.lock().take() {
+            if let Some(mut i) = i.process.into_inner() {
+                let _ = i.kill();
+                let _ = i.wait();
+            }
+        }
+
+        if let Err(e) = self.write_key(&self.process.secret.read()) {
+            self.log(
+                LogLevel::Error,
+                format!("Error restoring key file of `{}`: {e}", self.name),
+            );
+        }
+    }
+
+    fn next_exited(&self) -> Option<String> {
+        let next = self.next.lock();
+        let mut process = next.as_ref()?.process.lock();
+        match process.as_mut()?.try_wait() {
+            Ok(Some(i)) => Some(i.to_string()),
+            _ => None,
+        }
+    }
+
+    fn ready(&self, pid: u32) -> bool {
+        let deploy = &self.config.deploy;
+        deploy.ready_port.map(|x| listening(pid, x)).unwrap_or(true)
+            && deploy.ready_url.as_deref().map(healthy).unwrap_or(true)
+            && (!self.config.notify.ready || self.notify_ready())
+    }
+
+    /// If the new instance sent `READY=1`, the old one can not since it has its own socket
+    fn notify_ready(&self) -> bool {
+        let next = self.next.lock();
+        let next = match next.as_ref() {
+            Some(i) => i,
+            None => return false,
+        };
+
+        self.read_notify(next);
+        let ready = next.report.read().as_ref().map(|x| x.ready);
+        ready.unwrap_or(false)
+    }
+}
+
+/// Checks if a process has a socket listening on a TCP port
+fn listening(pid: u32, port: u16) -> bool {
+    let mut inodes = Vec::new();
+    for i in ["/proc/net/tcp", "/proc/net/tcp6"] {
+        let table = match fs::read_to_string(i) {
+            Ok(i) => i,
+            Err(_) => continue,
+        };
+
+        // sl local_address rem_address st ... inode
+        for line in table.lines().skip(1) {
+            let cols = line.split_whitespace().collect::<Vec<_>>();
+            let local_port = cols
+                .get(1)
+                .and_then(|x| x.rsplit(':').next())
+                .and_then(|x| u16::from_str_radix(x, 16).ok());
+
+            // 0A is LISTEN
+            if local_port == Some(port) && cols.get(3) == Some(&"0A") {
+                if let Some(inode) = cols.get(9) {
+                    inodes.push(format!("socket:[{inode}]"));
+                }
+            }
+        }
+    }
+
+    let fds = match fs::read_dir(format!("/proc/{pid}/fd")) {
+        Ok(i) => i,
+        Err(_) => return false,
+    };
+
+    fds.filter_map(|x| fs::read_link(x.ok()?.path()).ok())
+        .any(|x| inodes.iter().any(|i| x.as_os_str() == i.as_str()))
+}
+
+/// Sends a GET request to an http url and checks for a 2xx response
+fn healthy(url: &str) -> bool {
+    misc::http_request("GET", url, &[], &[], Duration::from_secs(1))
+        .map(|(status, _)| (200..300).contains(&status))
+        .unwrap_or(false)
+}
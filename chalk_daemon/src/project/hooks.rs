@@ -0,0 +1,227 @@
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+use std::io;
+use std::os::unix::process::CommandExt;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use nonblock::NonBlockingReader;
+
+use super::Project;
+use crate::{misc, App, LogLevel, LogSource};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hook {
+    PreStart,
+    PostStart,
+    PreStop,
+    PostStop,
+    PreDeploy,
+    PostDeploy,
+}
+
+/// A hook command with everything it needs to run away from its project
+struct HookCommand {
+    hook: Hook,
+    project: String,
+    command: String,
+    dir: PathBuf,
+    env: HashMap<String, String>,
+    timeout: u64,
+}
+
+struct HookOutput {
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+    result: Result<(), String>,
+}
+
+impl Project {
+    /// Runs a hook if the app has one and waits for it.
+    /// Its output is added to the app's output.
+    /// Errors if the hook fails, which should abort whatever a `pre_*` hook was run for.
+    pub fn run_hook(&self, hook: Hook) -> Result<(), String> {
+        let command = match self.hook_command(hook) {
+            Some(i) => i,
+            None => return Ok(()),
+        };
+
+        let output = command.run(&self.app);
+        self.record_hook(&command, &output);
+        output.result
+    }
+
+    /// Runs a hook on its own thread, for `post_*` hooks nothing waits on
+    pub fn spawn_hook(&self, hook: Hook) {
+        let command = match self.hook_command(hook) {
+            Some(i) => i,
+            None => return,
+        };
+
+        let app = self.app.clone();
+        thread::Builder::new()
+            .name("Hook".to_owned())
+            .spawn(move || {
+                let output = command.run(&app);
+                // The app could have been reloaded or removed while the hook ran
+                if let Some(i) = app.project(&command.project) {
+                    i.record_hook(&command, &output);
+                }
+            })
+            .unwrap();
+    }
+
+    /// Runs `f` between the `pre_deploy` and `post_deploy` hooks
+    pub fn with_deploy_hooks<T>(&self, f: impl FnOnce() -> Result<T, String>) -> Result<T, String> {
+        self.run_hook(Hook::PreDeploy)?;
+        let out = f()?;
+        self.spawn_hook(Hook::PostDeploy);
+        Ok(out)
+    }
+
+    fn hook_command(&self, hook: Hook) -> Option<HookCommand> {
+        let hooks = &self.config.hooks;
+        let command = match hook {
+            Hook::PreStart => &hooks.pre_start,
+            Hook::PostStart => &hooks.post_start,
+            Hook::PreStop => &hooks.pre_stop,
+            Hook::PostStop => &hooks.post_stop,
+            Hook::PreDeploy => &hooks.pre_deploy,
+            Hook::PostDeploy => &hooks.post_deploy,
+        };
+
+        // Same folder as the app, unless it does not exist yet (EX: `current` before the first deploy)
+        let mut dir = self.project_path.join(&self.config.run.path);
+        if !dir.is_dir() {
+            dir = self.project_path.to_owned();
+        }
+
+        Some(HookCommand {
+            hook,
+            project: self.name.to_owned(),
+            command: command.as_ref()?.to_owned(),
+            dir,
+            env: self.config.run.environment_vars.to_owned(),
+            timeout: hooks.timeout,
+        })
+    }
+
+    fn record_hook(&self, command: &HookCommand, output: &HookOutput) {
+        let mut stdout = self.process.stdout.write();
+        stdout.extend(format!("[{} hook] $ {}\n", command.hook, command.command).as_bytes());
+        stdout.extend(&output.stdout);
+        drop(stdout);
+
+        let mut stderr = self.process.stderr.write();
+        stderr.extend(&output.stderr);
+        if let Err(e) = &output.result {
+            stderr.extend(format!("{e}\n").as_bytes());
+        }
+    }
+}
+
+impl HookCommand {
+    fn run(&self, app: &App) -> HookOutput {
+        app.log_from(
+            LogSource::App(self.project.to_owned()),
+            LogLevel::Debug,
+            format!("Running `{}` hook of `{}`", self.hook, self.project),
+        );
+
+        let mut stdout = Vec::new();
+        let mut stderr = Vec::new();
+        let result = self
+            .wait(&mut stdout, &mut stderr)
+            .map_err(|e| format!("`{}` hook failed: {e}", self.hook));
+
+        if let Err(e) = &result {
+            app.log_from(
+                LogSource::App(self.project.to_owned()),
+                LogLevel::Error,
+                format!("{e} (`{}`)", self.project),
+            );
+        }
+
+        HookOutput {
+            stdout,
+            stderr,
+            result,
+        }
+    }
+
+    fn wait(&self, stdout: &mut Vec<u8>, stderr: &mut Vec<u8>) -> Result<(), String> {
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(&self.command)
+            .current_dir(&self.dir)
+            .envs(&self.env)
+            .env("CHALK_HOOK", self.hook.to_string())
+            // So a timeout can kill everything the hook started
+            .process_group(0)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Error starting: {e}"))?;
+
+        let pipes = child
+            .stdout
+            .take()
+            .map(NonBlockingReader::from_fd)
+            .zip(child.stderr.take().map(NonBlockingReader::from_fd));
+        let (mut out_reader, mut err_reader) = match pipes {
+            Some((Ok(i), Ok(j))) => (i, j),
+            _ => {
+                misc::kill_group(&mut child);
+                return Err("Error reading output".to_owned());
+            }
+        };
+        let mut read = |stdout: &mut Vec<u8>, stderr: &mut Vec<u8>| -> io::Result<()> {
+            out_reader.read_available(stdout)?;
+            err_reader.read_available(stderr)?;
+            Ok(())
+        };
+
+        let timeout = Duration::from_secs(self.timeout);
+        let start = Instant::now();
+        let status = loop {
+            let _ = read(stdout, stderr);
+            match child.try_wait() {
+                Ok(Some(i)) => break i,
+                Ok(None) => {}
+                Err(e) => {
+                    misc::kill_group(&mut child);
+                    return Err(format!("Error waiting: {e}"));
+                }
+            }
+
+            if start.elapsed() >= timeout {
+                misc::kill_group(&mut child);
+                return Err(format!("Timed out after {}s", self.timeout));
+            }
+
+            thread::sleep(Duration::from_millis(100));
+        };
+        let _ = read(stdout, stderr);
+
+        match status.success() {
+            true => Ok(()),
+            false => Err(status.to_string()),
+        }
+    }
+}
+
+impl Display for Hook {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::PreStart => "pre_start",
+            Self::PostStart => "post_start",
+            Self::PreStop => "pre_stop",
+            Self::PostStop => "post_stop",
+            Self::PreDeploy => "pre_deploy",
+            Self::PostDeploy => "post_deploy",
+        })
+    }
+}
@@ -1,9 +1,16 @@
-use std::fs;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::mem;
+use std::os::unix::fs::OpenOptionsExt;
 use std::path::PathBuf;
 use std::process::{self, Child, ChildStderr, ChildStdout, Stdio};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
 
+use chalk_client::control::{Nonces, KEY_FILE_ENV, REPORT_URL_ENV};
+use chalk_client::models::{AppReport, LogRecord};
 use chrono::Utc;
 use nix::{
     sys::signal::{self, Signal},
@@ -11,17 +18,35 @@ use nix::{
 };
 use nonblock::NonBlockingReader;
 use parking_lot::{Mutex, RwLock};
-use rand::distributions::Alphanumeric;
 use rand::{thread_rng, Rng};
-use serde_derive::Serialize;
 
-use crate::{App, LogType};
+use crate::{App, LogLevel, LogSource};
 
+mod build;
+mod bundle;
 mod config;
-use config::ProjectConfig;
+mod control;
+mod deploy;
+mod hooks;
+mod notify;
+mod records;
+mod release;
+mod report;
+pub use chalk_client::models::ProjectStatus;
+pub use config::{
+    DeployStrategy, GitStrategy, ProjectConfig, ProjectGitConfig, ProjectWebhookConfig,
+};
+pub use hooks::Hook;
+use notify::NotifySocket;
 
 type Reader<T> = Mutex<Option<NonBlockingReader<T>>>;
 
+/// File in the project folder the interface secret is passed to the app through
+const KEY_FILE: &str = ".chalk-key";
+
+/// How long to wait for an app to stop before killing it
+pub const STOP_TIMEOUT: Duration = Duration::from_secs(10);
+
 pub struct Project {
     // == Static Settings ==
     /// The app friendly name
@@ -30,6 +55,9 @@ pub struct Project {
     /// app config
     pub config: ProjectConfig,
 
+    /// A changed config waiting for the app to stop before being applied
+    pub staged_config: Mutex<Option<ProjectConfig>>,
+
     // /// Git repo info
     // pub git_info: Option<GitInfo>,
     //
@@ -40,7 +68,20 @@ pub struct Project {
     /// | config.toml
     /// | repo
     /// | | ...
-    /// | binary
+    /// | binary -> current/binary
+    /// | current -> releases/2
+    /// | releases
+    /// | | 1
+    /// | | | binary
+    /// | | | .release.toml
+    /// | | 2
+    /// | | | ... (bundle contents)
+    /// | | | data -> ../../shared/data
+    /// | | | .release.toml
+    /// | shared
+    /// | | data
+    /// | build.log
+    /// | .chalk-key
     /// ```
     pub project_path: PathBuf,
 
@@ -48,18 +89,34 @@ pub struct Project {
     /// Current status of the process (for cli / automation?)
     pub status: RwLock<ProjectStatus>,
 
-    /// Used for secure communication to the child process
-    /// (Through the `CHALK-KEY` env var)
-    pub interface_secret: RwLock<String>,
+    /// Used to reject replayed reports
+    report_nonces: Nonces,
 
     /// Lower level process stuff
     pub process: Process,
 
+    /// New instance waiting to pass its readiness check during a blue/green deploy
+    pub next: Mutex<Option<Process>>,
+
+    /// Held while the app is deployed or restarted, so they run one at a time
+    pub deploy_lock: Arc<Mutex<()>>,
+
+    /// Held while a release is made, so two can not get the same id.
+    /// Not the deploy lock, releases are made while it is held.
+    release_lock: Arc<Mutex<()>>,
+
     // == MISC ==
     /// Reference to app
     app: Arc<App>,
 }
 
+/// A just started instance of the app, see [`Project::spawn`]
+struct Instance {
+    child: Child,
+    secret: String,
+    notify: Option<NotifySocket>,
+}
+
 pub struct Process {
     /// Start Timestamp
     pub uptime: AtomicU64,
@@ -78,14 +135,22 @@ pub struct Process {
 
     /// Process stderr
     pub stderr: RwLock<Vec<u8>>,
-}
 
-#[derive(Serialize, Debug, PartialEq, Eq)]
-#[serde(rename_all = "snake_case")]
-pub enum ProjectStatus {
-    Running,
-    Stopped,
-    Crashed(Option<i32>),
+    /// Used to sign control requests to this instance and check its reports.
+    /// Changed on every start and passed through a file only the daemon's user can read (see [`KEY_FILE_ENV`]).
+    pub secret: RwLock<String>,
+
+    /// What the app reported about itself since this instance was started
+    pub report: RwLock<Option<AppReport>>,
+
+    /// sd_notify socket of this instance, its file is removed once dropped
+    notify: Mutex<Option<NotifySocket>>,
+
+    /// Structured logs parsed from JSON lines of the output
+    pub records: RwLock<Vec<LogRecord>>,
+
+    /// How much of stdout / stderr has been parsed into records
+    parsed: Mutex<(usize, usize)>,
 }
 
 impl Project {
@@ -93,91 +158,255 @@ impl Project {
         Self {
             name: raw.name.to_owned(),
             config: raw,
+            staged_config: Mutex::new(None),
             project_path: path,
             status: RwLock::new(ProjectStatus::Stopped),
             process: Process::new(),
-            interface_secret: RwLock::new(
-                thread_rng()
-                    .sample_iter(Alphanumeric)
-                    .take(10)
-                    .map(char::from)
-                    .collect(),
-            ),
+            next: Mutex::new(None),
+            report_nonces: Nonces::default(),
+            deploy_lock: Arc::new(Mutex::new(())),
+            release_lock: Arc::new(Mutex::new(())),
             app,
         }
     }
 
-    pub fn start(&self) {
+    /// A stopped project in `path` for tests, `extra` is added to the end of its config (EX: `[releases]`)
+    #[cfg(test)]
+    pub fn test(path: PathBuf, extra: &str) -> Self {
+        use directories::ProjectDirs;
+
+        use crate::config::Config;
+
+        let raw = format!(
+            "name = \"test\"\napi_token = \"test-token\"\n\n[run]\npath = \".\"\ncommand = \"app\"\narguments = []\nenvironment_vars = {{}}\n\n[git]\n{extra}"
+        );
+        let dirs = ProjectDirs::from("com", "connorcode", "chalk").unwrap();
+        let app = App::from_config(dirs, Config::default());
+        Self::from_raw(toml::from_str(&raw).unwrap(), path, Arc::new(app))
+    }
+
+    /// Starts the app after its `pre_start` hook.
+    /// Errors are logged and returned.
+    pub fn start(&self) -> Result<(), String> {
         let binary_path = self.project_path.join(&self.config.run.command);
 
-        if *self.status.read() == ProjectStatus::Running {
-            self.app.log(
-                LogType::Error,
+        if self.status.read().is_running() {
+            self.log(
+                LogLevel::Error,
                 format!("Process already started `{}`", self.name),
             );
-            return;
+            return Err("App Already Running".to_owned());
         }
 
+        self.run_hook(Hook::PreStart)?;
+
         if !binary_path.exists() {
-            self.app.log(
-                LogType::Error,
+            self.log(
+                LogLevel::Error,
                 format!("No runnable binary `{}`", self.name),
             );
-            return;
+            return Err("No runnable binary".to_owned());
         }
 
-        self.app
-            .log(LogType::Info, format!("Starting `{}`", self.name));
-        let mut child = match process::Command::new(binary_path)
+        self.log(LogLevel::Info, format!("Starting `{}`", self.name));
+        let instance = match self.spawn() {
+            Ok(i) => i,
+            Err(e) => {
+                let e = format!(
+                    "Error starting `{}`'s executable `{}`: {}",
+                    self.name, self.config.run.command, e
+                );
+                self.log(LogLevel::Error, &e);
+                return Err(e);
+            }
+        };
+
+        self.process.attach(instance);
+        // With `notify.ready` the `post_start` hook runs once the app is ready
+        if self.config.notify.ready {
+            *self.status.write() = ProjectStatus::Starting;
+        } else {
+            *self.status.write() = ProjectStatus::Running;
+            self.spawn_hook(Hook::PostStart);
+        }
+        Ok(())
+    }
+
+    /// Starts a new instance of the app with a new interface secret.
+    /// The secret is only used once the instance is attached to a [`Process`].
+    fn spawn(&self) -> io::Result<Instance> {
+        let secret = hex::encode(thread_rng().gen::<[u8; 32]>());
+        let key_file = self.write_key(&secret)?;
+
+        let mut command = process::Command::new(self.project_path.join(&self.config.run.command));
+        command
             .current_dir(self.project_path.join(&self.config.run.path))
             .args(&self.config.run.arguments)
             .envs(&self.config.run.environment_vars)
-            .env("CHALK-KEY", &*self.interface_secret.read())
+            .env(KEY_FILE_ENV, key_file)
+            .env(REPORT_URL_ENV, self.report_url())
             .stdin(Stdio::null())
             .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()
-        {
-            Ok(i) => i,
+            .stderr(Stdio::piped());
+
+        // Same variables systemd passes
+        let notify = match self.bind_notify() {
+            Ok(i) => {
+                command.env("NOTIFY_SOCKET", &i.path);
+                if let Some(i) = self.config.notify.watchdog {
+                    command.env("WATCHDOG_USEC", (i * 1_000_000).to_string());
+                }
+                Some(i)
+            }
             Err(e) => {
-                self.app.log(
-                    LogType::Error,
-                    format!(
-                        "Error starting `{}`'s executable `{}`: {}",
-                        self.name, self.config.run.command, e
-                    ),
+                self.log(
+                    LogLevel::Error,
+                    format!("Error making notify socket for `{}`: {e}", self.name),
                 );
-                return;
+                None
             }
         };
 
-        *self.process.stdout_reader.lock() =
-            Some(NonBlockingReader::from_fd(child.stdout.take().unwrap()).unwrap());
-        *self.process.stderr_reader.lock() =
-            Some(NonBlockingReader::from_fd(child.stderr.take().unwrap()).unwrap());
-        *self.process.process.lock() = Some(child);
-        *self.status.write() = ProjectStatus::Running;
-        self.process
-            .uptime
-            .store(Utc::now().timestamp() as u64, Ordering::Relaxed);
+        Ok(Instance {
+            child: command.spawn()?,
+            secret,
+            notify,
+        })
+    }
+
+    /// Writes an interface secret to the key file, returning its path
+    fn write_key(&self, secret: &str) -> io::Result<PathBuf> {
+        let path = self.project_path.join(KEY_FILE);
+
+        // Recreated so the mode is always set
+        match fs::remove_file(&path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&path)?;
+        file.write_all(secret.as_bytes())?;
+        Ok(path)
+    }
+
+    /// Sends a signal to stop the app, after its `pre_stop` hook if it is running
+    pub fn stop(&self, sig: Signal) -> Result<(), String> {
+        let running = self.status.read().is_running();
+        if running {
+            self.run_hook(Hook::PreStop)?;
+        }
+        self.signal(sig);
+        Ok(())
+    }
+
+    /// Sends a signal to the app without running any hooks
+    pub fn signal(&self, sig: Signal) {
+        // A new instance that is still starting gets stopped too
+        if let Some(i) = self.next.lock().as_ref() {
+            i.signal(sig);
+        }
+
+        if self.process.signal(sig) {
+            self.log(
+                LogLevel::Info,
+                format!("Stopping `{}` with `{}`", self.name, sig),
+            );
+        }
+    }
+
+    /// Stops the process and waits for it to exit.
+    /// If it is still running after `timeout` it gets killed.
+    pub fn stop_wait(&self, sig: Signal, timeout: Duration) -> Result<(), String> {
+        self.stop(sig)?;
+        self.wait_exit(timeout);
+
+        let was = mem::replace(&mut *self.status.write(), ProjectStatus::Stopped);
+        self.process.uptime.store(0, Ordering::Relaxed);
+        self.process.notify.lock().take();
+        if was.is_running() {
+            self.spawn_hook(Hook::PostStop);
+        }
+        Ok(())
+    }
+
+    /// Waits for the process to exit, killing it after `timeout`
+    fn wait_exit(&self, timeout: Duration) {
+        let start = Instant::now();
+        loop {
+            let mut raw_process = self.process.process.lock();
+            let process = match raw_process.as_mut() {
+                Some(i) => i,
+                None => return,
+            };
+
+            if !matches!(process.try_wait(), Ok(None)) {
+                break;
+            }
+
+            if start.elapsed() >= timeout {
+                self.log(
+                    LogLevel::Warn,
+                    format!("`{}` did not stop in time. Killing it.", self.name),
+                );
+                let _ = process.kill();
+                let _ = process.wait();
+                break;
+            }
+
+            drop(raw_process);
+            thread::sleep(Duration::from_millis(100));
+        }
     }
 
-    pub fn stop(&self, sig: Signal) {
-        let mut raw_process = self.process.process.lock();
-        if raw_process.is_none() {
+    /// Swaps in the staged config of any project that is not running
+    pub fn apply_staged(app: Arc<App>) {
+        if !app
+            .projects
+            .read()
+            .iter()
+            .any(|x| x.staged_config.lock().is_some())
+        {
             return;
         }
 
-        self.app.log(
-            LogType::Info,
-            format!("Stopping `{}` with `{}`", self.name, sig),
-        );
+        let mut projects = app.projects.write();
+        for i in projects
+            .iter_mut()
+            .filter(|x| !x.status.read().is_running())
+        {
+            let config = match i.staged_config.lock().take() {
+                Some(i) => i,
+                None => continue,
+            };
+
+            i.log(
+                LogLevel::Info,
+                format!("Applying new config for `{}`", i.name),
+            );
+            let mut new = Self::from_raw(config, i.project_path.to_owned(), app.clone());
+            new.share_locks(i);
+            *i = Arc::new(new);
+        }
+    }
 
-        let process = raw_process.as_mut().unwrap();
-        let _ = signal::kill(Pid::from_raw(process.id() as i32), sig);
+    /// Uses the locks of the project this one replaces,
+    /// so deploys and releases still running on the old one keep out new ones
+    pub fn share_locks(&mut self, old: &Project) {
+        self.deploy_lock = old.deploy_lock.clone();
+        self.release_lock = old.release_lock.clone();
     }
 
     pub fn poll(&self) {
+        // Locked before `process`, same as a blue/green switch
+        let next = self.next.lock();
+        if let Some(i) = next.as_ref() {
+            i.read_output();
+            self.read_notify(i);
+        }
+
         let mut process = self.process.process.lock();
         if process.is_none() {
             return;
@@ -187,59 +416,60 @@ impl Project {
 
         // Process stdout / stderr
         // This is nonblocking due to the `NonBlockingReader`
-        self.process
-            .stdout_reader
-            .lock()
-            .as_mut()
-            .unwrap()
-            .read_available(self.process.stdout.write().as_mut())
-            .unwrap();
+        self.process.read_output();
+        self.read_notify(&self.process);
 
-        self.process
-            .stderr_reader
-            .lock()
-            .as_mut()
-            .unwrap()
-            .read_available(self.process.stderr.write().as_mut())
-            .unwrap();
+        // The old instance exiting during a switch is expected
+        if next.is_some() {
+            return;
+        }
+        drop(next);
 
         // Set App Status
         if let Some(i) = process.try_wait().unwrap() {
             self.process.uptime.store(0, Ordering::Relaxed);
+            self.process.notify.lock().take();
 
-            if i.success() {
-                if *self.status.read() != ProjectStatus::Stopped {
-                    *self.status.write() = ProjectStatus::Stopped;
-                    self.app.log(
-                        LogType::Info,
-                        format!("Project `{}` has stopped", self.name),
-                    );
-                }
+            // Only the first poll after the exit changes the status
+            let mut status = self.status.write();
+            if !status.is_running() {
                 return;
             }
 
-            if !matches!(*self.status.read(), ProjectStatus::Crashed(_)) {
-                *self.status.write() = ProjectStatus::Crashed(i.code());
-                self.app.log(
-                    LogType::Error,
+            if i.success() {
+                *status = ProjectStatus::Stopped;
+                drop(status);
+                self.log(
+                    LogLevel::Info,
+                    format!("Project `{}` has stopped", self.name),
+                );
+            } else {
+                *status = ProjectStatus::Crashed(i.code());
+                drop(status);
+                self.log(
+                    LogLevel::Error,
                     format!("Project `{}` has crashed! ({:?})", self.name, i.code()),
                 );
             }
+            self.spawn_hook(Hook::PostStop);
+            return;
         }
+
+        self.check_watchdog();
     }
 
     pub fn any_running(app: Arc<App>) -> bool {
         app.projects
             .read()
             .iter()
-            .filter(|x| *x.status.read() == ProjectStatus::Running)
+            .filter(|x| x.status.read().is_running())
             .count()
             == 0
     }
 
     pub fn load_project(path: PathBuf, app: Arc<App>) -> Option<Project> {
         app.log(
-            LogType::Info,
+            LogLevel::Info,
             format!(
                 "Loading app from `{}`",
                 path.file_name().unwrap().to_string_lossy()
@@ -249,7 +479,10 @@ impl Project {
         // Read config
         let app_config = path.join("config.toml");
         if !app_config.exists() {
-            app.log(LogType::Error, "^ App config file not found! (config.toml)");
+            app.log(
+                LogLevel::Error,
+                "^ App config file not found! (config.toml)",
+            );
             return None;
         }
         let raw_config = fs::read_to_string(app_config).expect("Error reading config file");
@@ -258,7 +491,7 @@ impl Project {
         let config = match toml::from_str::<ProjectConfig>(&raw_config) {
             Ok(i) => i,
             Err(e) => {
-                app.log(LogType::Error, format!("^ Invalid app config: {e}"));
+                app.log(LogLevel::Error, format!("^ Invalid app config: {e}"));
                 return None;
             }
         };
@@ -266,33 +499,111 @@ impl Project {
         Some(Self::from_raw(config, path, app))
     }
 
-    pub fn find_projects(app: Arc<App>) -> Vec<Project> {
-        let app_dir = app.app_dir.preference_dir().join(&app.config.app_dir);
+    /// Loads any folders in the app dir that are not already loaded.
+    /// Returns the names of the new projects.
+    pub fn rescan(app: Arc<App>) -> Vec<String> {
+        let app_dir = app.apps_path();
+        let mut projects = app.projects.write();
         let mut out = Vec::new();
 
         // Make app dir if not eggists
         if !app_dir.exists() {
-            app.log(LogType::Info, "Apps folder not found. Making one.");
+            app.log(LogLevel::Info, "Apps folder not found. Making one.");
             fs::create_dir_all(&app_dir).unwrap();
         }
 
-        for i in fs::read_dir(app_dir)
+        let dirs = fs::read_dir(app_dir)
             .unwrap()
-            .map(|x| x.unwrap())
-            .filter(|x| x.path().is_dir())
-        {
-            if let Some(i) = Self::load_project(i.path(), app.clone()) {
-                out.push(i);
+            .map(|x| x.unwrap().path())
+            .filter(|x| x.is_dir() && !projects.iter().any(|y| &y.project_path == x))
+            .collect::<Vec<_>>();
+
+        for i in dirs {
+            let project = match Self::load_project(i, app.clone()) {
+                Some(i) => i,
+                None => continue,
+            };
+
+            if let Err(e) = Self::check_unique(&project.config, projects.iter(), &app) {
+                app.log(LogLevel::Error, format!("^ {e}"));
+                continue;
             }
+
+            out.push(project.name.to_owned());
+            projects.push(Arc::new(project));
         }
 
         out
     }
-}
 
-impl ProjectStatus {
-    pub fn is_running(&self) -> bool {
-        *self == ProjectStatus::Running
+    /// Stops and unloads any projects whose folder is gone.
+    /// Returns the names of the removed projects.
+    pub fn unload_missing(app: Arc<App>) -> Vec<String> {
+        // Cloned out so the list is not locked while they stop
+        let missing = app
+            .projects
+            .read()
+            .iter()
+            .filter(|x| !x.project_path.exists())
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut out = Vec::new();
+
+        for i in missing {
+            let _deploying = i.deploy_lock.lock();
+            app.log(
+                LogLevel::Info,
+                format!("Folder for `{}` was removed. Unloading it.", i.name),
+            );
+            // No hooks, they would run in the missing folder
+            i.signal(Signal::SIGINT);
+            i.wait_exit(STOP_TIMEOUT);
+            out.push(i.name.to_owned());
+        }
+
+        if !out.is_empty() {
+            app.projects.write().retain(|x| x.project_path.exists());
+        }
+
+        out
+    }
+
+    /// Makes sure a new project's name and token are not already in use
+    pub fn check_unique<'a>(
+        config: &ProjectConfig,
+        projects: impl Iterator<Item = &'a Arc<Project>>,
+        app: &App,
+    ) -> Result<(), &'static str> {
+        if config.api_token == app.config.read().api.token {
+            return Err("Api token already in use");
+        }
+
+        for i in projects {
+            if i.name == config.name {
+                return Err("App name already in use");
+            }
+
+            if i.config.api_token == config.api_token {
+                return Err("Api token already in use");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Logs something that happened to this app
+    pub fn log<T: AsRef<str>>(&self, level: LogLevel, text: T) {
+        self.app
+            .log_from(LogSource::App(self.name.to_owned()), level, text);
+    }
+
+    /// Checks if a name can be used as a project folder
+    pub fn valid_name(name: &str) -> bool {
+        !name.is_empty()
+            && !name.starts_with('.')
+            && name
+                .chars()
+                .all(|x| x.is_ascii_alphanumeric() || matches!(x, '-' | '_' | '.'))
     }
 }
 
@@ -305,6 +616,67 @@ impl Process {
             stderr_reader: Mutex::new(None),
             stdout: RwLock::new(Vec::new()),
             stderr: RwLock::new(Vec::new()),
+            secret: RwLock::new(String::new()),
+            report: RwLock::new(None),
+            notify: Mutex::new(None),
+            records: RwLock::new(Vec::new()),
+            parsed: Mutex::new((0, 0)),
+        }
+    }
+
+    /// Takes the output pipes of a new instance and keeps its handle, secret and notify socket
+    fn attach(&self, instance: Instance) {
+        let mut child = instance.child;
+        *self.secret.write() = instance.secret;
+        *self.report.write() = None;
+        *self.notify.lock() = instance.notify;
+        *self.stdout_reader.lock() =
+            Some(NonBlockingReader::from_fd(child.stdout.take().unwrap()).unwrap());
+        *self.stderr_reader.lock() =
+            Some(NonBlockingReader::from_fd(child.stderr.take().unwrap()).unwrap());
+        *self.process.lock() = Some(child);
+        self.uptime
+            .store(Utc::now().timestamp() as u64, Ordering::Relaxed);
+    }
+
+    /// Sends a signal to the process.
+    /// Returns false if there is no process.
+    fn signal(&self, sig: Signal) -> bool {
+        match self.process.lock().as_ref() {
+            Some(i) => {
+                let _ = signal::kill(Pid::from_raw(i.id() as i32), sig);
+                true
+            }
+            None => false,
         }
     }
+
+    /// Reads any new stdout / stderr of the process
+    fn read_output(&self) {
+        if let Some(i) = self.stdout_reader.lock().as_mut() {
+            i.read_available(self.stdout.write().as_mut()).unwrap();
+        }
+
+        if let Some(i) = self.stderr_reader.lock().as_mut() {
+            i.read_available(self.stderr.write().as_mut()).unwrap();
+        }
+
+        self.read_records();
+    }
+
+    /// Moves the handle and output of another process into this one
+    fn replace(&self, other: Process) {
+        *self.process.lock() = other.process.into_inner();
+        *self.stdout_reader.lock() = other.stdout_reader.into_inner();
+        *self.stderr_reader.lock() = other.stderr_reader.into_inner();
+        *self.stdout.write() = other.stdout.into_inner();
+        *self.stderr.write() = other.stderr.into_inner();
+        *self.secret.write() = other.secret.into_inner();
+        *self.report.write() = other.report.into_inner();
+        *self.notify.lock() = other.notify.into_inner();
+        *self.records.write() = other.records.into_inner();
+        *self.parsed.lock() = other.parsed.into_inner();
+        self.uptime
+            .store(other.uptime.into_inner(), Ordering::Relaxed);
+    }
 }
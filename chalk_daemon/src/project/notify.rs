@@ -0,0 +1,147 @@
+use std::fs;
+use std::io;
+use std::os::unix::net::UnixDatagram;
+use std::path::PathBuf;
+use std::ptr;
+use std::time::{Duration, Instant};
+
+use chalk_client::models::AppReport;
+use chrono::Utc;
+use rand::{thread_rng, Rng};
+
+use super::{Hook, Process, Project, ProjectStatus};
+use crate::LogLevel;
+
+/// The sd_notify socket of one instance of the app
+pub struct NotifySocket {
+    socket: UnixDatagram,
+    pub path: PathBuf,
+    /// Last `WATCHDOG=1`, or when the socket was made
+    watchdog: Instant,
+}
+
+impl Drop for NotifySocket {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+impl Project {
+    /// Makes a new notify socket for an instance that is about to start.
+    /// Each instance gets its own path, so an old one still running during a blue/green switch can not report for the new one.
+    pub(super) fn bind_notify(&self) -> io::Result<NotifySocket> {
+        let name = format!(
+            ".notify-{}.sock",
+            hex::encode(thread_rng().gen::<[u8; 4]>())
+        );
+        let path = self.project_path.join(name);
+        let socket = UnixDatagram::bind(&path)?;
+        socket.set_nonblocking(true)?;
+
+        Ok(NotifySocket {
+            socket,
+            path,
+            watchdog: Instant::now(),
+        })
+    }
+
+    /// Handles any messages waiting on the notify socket of an instance
+    pub(super) fn read_notify(&self, process: &Process) {
+        let mut buf = [0; 4096];
+        loop {
+            let len = match process
+                .notify
+                .lock()
+                .as_ref()
+                .map(|x| x.socket.recv(&mut buf))
+            {
+                Some(Ok(i)) => i,
+                _ => return,
+            };
+
+            let message = String::from_utf8_lossy(&buf[..len]).into_owned();
+            for (key, value) in message.lines().filter_map(|x| x.split_once('=')) {
+                self.notify_message(process, key, value);
+            }
+        }
+    }
+
+    /// Only the current instance changes the app's status, a new one waiting for a blue/green switch just updates its report
+    fn notify_message(&self, process: &Process, key: &str, value: &str) {
+        let current = ptr::eq(process, &self.process);
+        match (key, value) {
+            ("READY", "1") => {
+                update_report(process, |x| x.ready = true);
+                if !current {
+                    return;
+                }
+
+                let mut status = self.status.write();
+                if *status == ProjectStatus::Starting {
+                    *status = ProjectStatus::Running;
+                    drop(status);
+                    self.log(LogLevel::Info, format!("`{}` is ready", self.name));
+                    self.spawn_hook(Hook::PostStart);
+                }
+            }
+            ("STATUS", _) => update_report(process, |x| x.status = Some(value.to_owned())),
+            ("STOPPING", "1") if current => {
+                let mut status = self.status.write();
+                if status.is_running() {
+                    *status = ProjectStatus::Stopping;
+                }
+            }
+            ("WATCHDOG", "1") => {
+                if let Some(i) = process.notify.lock().as_mut() {
+                    i.watchdog = Instant::now();
+                }
+            }
+            ("WATCHDOG", "trigger") if current => self.watchdog_expired(),
+            ("MAINPID", _) => {
+                if let Ok(i) = value.parse() {
+                    update_report(process, |x| x.main_pid = Some(i));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Restarts the app if it has not sent `WATCHDOG=1` within its watchdog interval
+    pub(super) fn check_watchdog(&self) {
+        let interval = match self.config.notify.watchdog {
+            Some(i) => Duration::from_secs(i),
+            None => return,
+        };
+        if *self.status.read() != ProjectStatus::Running {
+            return;
+        }
+
+        let expired = match self.process.notify.lock().as_ref() {
+            Some(i) => i.watchdog.elapsed() > interval,
+            None => false,
+        };
+        if expired {
+            self.watchdog_expired();
+        }
+    }
+
+    fn watchdog_expired(&self) {
+        // Only restart once per missed interval
+        if let Some(i) = self.process.notify.lock().as_mut() {
+            i.watchdog = Instant::now();
+        }
+
+        self.log(
+            LogLevel::Warn,
+            format!("`{}` missed its watchdog, it is probably hung", self.name),
+        );
+        self.restart_later();
+    }
+}
+
+fn update_report(process: &Process, f: impl FnOnce(&mut AppReport)) {
+    let mut report = process.report.write();
+    let report = report.get_or_insert_with(AppReport::default);
+    f(report);
+    report.time = Utc::now().timestamp();
+}
@@ -0,0 +1,203 @@
+use chalk_client::models::{LogLevel, LogRecord};
+use chrono::Utc;
+use serde_json::{Map, Value};
+
+use super::Process;
+
+/// Most structured logs kept per process, the oldest are dropped first
+const MAX_RECORDS: usize = 10_000;
+
+// Keys checked (in order) for each part of a record.
+// Covers the JSON formats of tracing-subscriber, slog, bunyan / pino and `afire_integration::Logger`.
+const LEVEL_KEYS: &[&str] = &["level", "lvl", "severity"];
+const MESSAGE_KEYS: &[&str] = &["message", "msg"];
+const TARGET_KEYS: &[&str] = &["target", "logger", "module"];
+
+impl Process {
+    /// Parses the lines written since the last call into structured logs.
+    /// Lines that are not a JSON object with a message are left as plain output.
+    pub(super) fn read_records(&self) {
+        let mut parsed = self.parsed.lock();
+        let (stdout, stderr) = &mut *parsed;
+        let now = Utc::now().timestamp();
+        let mut new = Vec::new();
+
+        for (output, parsed) in [(&self.stdout, stdout), (&self.stderr, stderr)] {
+            let output = output.read();
+            let unread = &output[*parsed..];
+
+            // Keep partial lines for when the rest is written
+            let end = match unread.iter().rposition(|x| *x == b'\n') {
+                Some(i) => i + 1,
+                None => continue,
+            };
+
+            new.extend(
+                unread[..end]
+                    .split(|x| *x == b'\n')
+                    .filter_map(|x| parse(x, now)),
+            );
+            *parsed += end;
+        }
+
+        if new.is_empty() {
+            return;
+        }
+
+        let mut records = self.records.write();
+        records.extend(new);
+        let over = records.len().saturating_sub(MAX_RECORDS);
+        records.drain(..over);
+    }
+}
+
+/// Parses one line of output into a record
+fn parse(line: &[u8], time: i64) -> Option<LogRecord> {
+    let line = line.trim_ascii();
+    if !line.starts_with(b"{") {
+        return None;
+    }
+
+    let mut object = match serde_json::from_slice::<Value>(line) {
+        Ok(Value::Object(i)) => i,
+        _ => return None,
+    };
+
+    // tracing-subscriber nests the message and fields of an event
+    if let Some(Value::Object(fields)) = object.remove("fields") {
+        object.extend(fields);
+    }
+
+    let message = match take(&mut object, MESSAGE_KEYS)? {
+        Value::String(i) => i,
+        i => i.to_string(),
+    };
+
+    let level = match take(&mut object, LEVEL_KEYS) {
+        Some(Value::String(i)) => LogLevel::from_name(&i),
+        Some(Value::Number(i)) => i.as_u64().and_then(LogLevel::from_number),
+        _ => None,
+    };
+
+    let target = match take(&mut object, TARGET_KEYS) {
+        Some(Value::String(i)) => Some(i),
+        _ => None,
+    };
+
+    Some(LogRecord {
+        time,
+        level: level.unwrap_or(LogLevel::Info),
+        message,
+        target,
+        fields: object.into_iter().collect(),
+    })
+}
+
+/// Removes the first of `keys` that is in the object
+fn take(object: &mut Map<String, Value>, keys: &[&str]) -> Option<Value> {
+    keys.iter().find_map(|x| object.remove(*x))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn record(line: &str) -> LogRecord {
+        parse(line.as_bytes(), 0).unwrap()
+    }
+
+    fn write(process: &Process, text: &str) {
+        process.stdout.write().extend_from_slice(text.as_bytes());
+        process.read_records();
+    }
+
+    fn messages(process: &Process) -> Vec<String> {
+        let records = process.records.read();
+        records.iter().map(|x| x.message.to_owned()).collect()
+    }
+
+    #[test]
+    fn parses_tracing_subscriber() {
+        let record = record(
+            r#"{"timestamp":"2024-01-01T00:00:00Z","level":"WARN","fields":{"message":"slow query","elapsed":5},"target":"app::db"}"#,
+        );
+
+        assert_eq!(record.level, LogLevel::Warn);
+        assert_eq!(record.message, "slow query");
+        assert_eq!(record.target.as_deref(), Some("app::db"));
+        assert_eq!(record.fields.get("elapsed"), Some(&json!(5)));
+        assert!(record.fields.contains_key("timestamp"));
+        assert!(!record.fields.contains_key("fields"));
+    }
+
+    #[test]
+    fn parses_other_keys() {
+        let slog = record(r#"{"msg":"started","lvl":"debug","module":"app"}"#);
+        assert_eq!(slog.level, LogLevel::Debug);
+        assert_eq!(slog.message, "started");
+        assert_eq!(slog.target.as_deref(), Some("app"));
+
+        let other = record(r#"{"message":"failed","severity":"CRITICAL","logger":"db"}"#);
+        assert_eq!(other.level, LogLevel::Error);
+        assert_eq!(other.target.as_deref(), Some("db"));
+
+        // Messages that are not strings are kept as JSON
+        assert_eq!(record(r#"{"msg":{"a":1}}"#).message, r#"{"a":1}"#);
+        // Unknown levels are info
+        assert_eq!(
+            record(r#"{"msg":"x","level":"loud"}"#).level,
+            LogLevel::Info
+        );
+    }
+
+    #[test]
+    fn parses_numeric_levels() {
+        for (level, expected) in [
+            (10, LogLevel::Trace),
+            (20, LogLevel::Debug),
+            (30, LogLevel::Info),
+            (40, LogLevel::Warn),
+            (60, LogLevel::Error),
+            (35, LogLevel::Info),
+        ] {
+            let line = format!(r#"{{"level":{level},"msg":"x"}}"#);
+            assert_eq!(record(&line).level, expected);
+        }
+    }
+
+    #[test]
+    fn skips_other_lines() {
+        for line in ["plain text", "[1, 2]", r#"{"level":"info"}"#, "{broken"] {
+            assert!(parse(line.as_bytes(), 0).is_none());
+        }
+    }
+
+    #[test]
+    fn carries_partial_lines() {
+        let process = Process::new();
+        write(&process, "{\"msg\":\"first\"}\n{\"msg\":\"sec");
+        assert_eq!(messages(&process), ["first"]);
+
+        write(&process, "ond\"}\nplain\n{\"msg\":");
+        assert_eq!(messages(&process), ["first", "second"]);
+
+        write(&process, "\"third\"}\n");
+        assert_eq!(messages(&process), ["first", "second", "third"]);
+    }
+
+    #[test]
+    fn caps_records() {
+        let process = Process::new();
+        let lines = (0..MAX_RECORDS + 5)
+            .map(|x| format!("{{\"msg\":\"{x}\"}}\n"))
+            .collect::<String>();
+        write(&process, &lines);
+
+        let messages = messages(&process);
+        assert_eq!(messages.len(), MAX_RECORDS);
+        assert_eq!(messages[0], "5");
+        assert_eq!(messages[MAX_RECORDS - 1], (MAX_RECORDS + 4).to_string());
+    }
+}
@@ -0,0 +1,475 @@
+use std::fs::{self, File, Permissions};
+use std::io;
+use std::os::unix::fs::{symlink, PermissionsExt};
+use std::path::{Component, Path, PathBuf};
+
+use chalk_client::{
+    models::{BundleKind, Release},
+    signing,
+};
+use chrono::Utc;
+use rand::{thread_rng, Rng};
+use sha2::{Digest, Sha256};
+
+use super::{bundle, Project};
+use crate::LogLevel;
+
+/// Release info, hidden so it does not clash with files in bundles
+const RELEASE_FILE: &str = ".release.toml";
+/// Where releases from before bundles kept their info
+const OLD_RELEASE_FILE: &str = "release.toml";
+
+impl Project {
+    /// Releases on disk, oldest first
+    pub fn releases(&self) -> Vec<Release> {
+        let mut out = self
+            .release_ids()
+            .into_iter()
+            .filter_map(|x| self.read_release(x))
+            .collect::<Vec<_>>();
+        out.sort_by_key(|x| x.id);
+        out
+    }
+
+    /// Reads the info of a release, from either of its file names
+    fn read_release(&self, id: u32) -> Option<Release> {
+        let path = self.release_path(id);
+        let raw = fs::read_to_string(path.join(RELEASE_FILE))
+            .or_else(|_| fs::read_to_string(path.join(OLD_RELEASE_FILE)))
+            .ok()?;
+        toml::from_str(&raw).ok()
+    }
+
+    /// The release `current` points to
+    pub fn current_release(&self) -> Option<u32> {
+        fs::read_link(self.project_path.join("current"))
+            .ok()?
+            .file_name()?
+            .to_str()?
+            .parse()
+            .ok()
+    }
+
+    /// The newest release before the current one, what a rollback goes back to by default
+    pub fn previous_release(&self) -> Option<u32> {
+        let current = self.current_release().unwrap_or(u32::MAX);
+        self.releases()
+            .into_iter()
+            .map(|x| x.id)
+            .filter(|x| *x < current)
+            .max()
+    }
+
+    /// Stores a new binary as a release, switches to it and removes old releases
+    pub fn add_release(
+        &self,
+        data: &[u8],
+        signature: Option<&str>,
+        commit: Option<String>,
+        uploader: &str,
+    ) -> Result<u32, String> {
+        let key = self.verify_signature(data, signature)?;
+        let checksum = hex::encode(Sha256::digest(data));
+        self.new_release(key, checksum, false, commit, uploader, |path| {
+            let binary = path.join(self.binary_name());
+            fs::write(&binary, data)
+                .and_then(|_| fs::set_permissions(&binary, Permissions::from_mode(0o755)))
+                .map_err(|e| format!("Error writing release: {e}"))
+        })
+    }
+
+    /// Makes a new release from an uploaded file, a binary or an archive to unpack if `bundle` is set.
+    /// The file is moved into the release, so it should be in the `releases` folder (see [`Project::upload_path`]).
+    /// Paths in `releases.preserve` are linked to `[project_path]/shared`.
+    pub fn add_upload(
+        &self,
+        file: &Path,
+        checksum: String,
+        bundle: Option<BundleKind>,
+        signature: Option<&str>,
+        uploader: &str,
+    ) -> Result<u32, String> {
+        // ed25519 needs the whole message, so only signed uploads are read into memory
+        let key = match self.config.releases.trusted_keys.is_empty() {
+            true => None,
+            false => {
+                let data = fs::read(file).map_err(|e| format!("Error reading upload: {e}"))?;
+                self.verify_signature(&data, signature)?
+            }
+        };
+
+        let limit = self.app.config.read().api.max_unpacked * 1024 * 1024;
+        self.new_release(key, checksum, bundle.is_some(), None, uploader, |path| {
+            let kind = match bundle {
+                Some(i) => i,
+                None => {
+                    let binary = path.join(self.binary_name());
+                    return fs::rename(file, &binary)
+                        .and_then(|_| fs::set_permissions(&binary, Permissions::from_mode(0o755)))
+                        .map_err(|e| format!("Error writing release: {e}"));
+                }
+            };
+
+            let archive = File::open(file).map_err(|e| format!("Error reading upload: {e}"))?;
+            bundle::extract(kind, archive, path, limit)?;
+            self.link_preserved(path)
+        })
+    }
+
+    /// A new file to stream an upload to, next to the releases so it can be moved into one.
+    /// Not a valid release id, so it is never taken for a release.
+    pub fn upload_path(&self) -> Result<PathBuf, String> {
+        let folder = self.project_path.join("releases");
+        fs::create_dir_all(&folder).map_err(|e| format!("Error creating releases: {e}"))?;
+        let name = format!(".upload-{}", hex::encode(thread_rng().gen::<[u8; 4]>()));
+        Ok(folder.join(name))
+    }
+
+    /// Stores a release filled by `fill` with the next id, then switches to it.
+    /// `key` is the trusted key that signed it and `checksum` the SHA-256 of the binary or archive.
+    fn new_release(
+        &self,
+        key: Option<String>,
+        checksum: String,
+        bundle: bool,
+        commit: Option<String>,
+        uploader: &str,
+        fill: impl FnOnce(&Path) -> Result<(), String>,
+    ) -> Result<u32, String> {
+        // Ids come from the release folders, so only one release is made at a time
+        let _creating = self.release_lock.lock();
+        self.import_binary()?;
+        let id = self.release_ids().into_iter().max().unwrap_or(0) + 1;
+        let release = Release {
+            id,
+            commit,
+            uploader: uploader.to_owned(),
+            checksum,
+            time: Utc::now().timestamp(),
+            bundle,
+            key,
+        };
+
+        self.write_release(&release, fill)?;
+        self.switch_release(id)?;
+        self.prune_releases();
+
+        self.log(
+            LogLevel::Info,
+            format!("Created release {id} of `{}`", self.name),
+        );
+        Ok(id)
+    }
+
+    /// Keeps a binary that was at `run.command` before any releases were made as release 1,
+    /// so the first switch does not replace it with a link and lose it
+    fn import_binary(&self) -> Result<(), String> {
+        let binary = self.project_path.join(&self.config.run.command);
+        let is_file = binary
+            .symlink_metadata()
+            .map(|x| x.is_file())
+            .unwrap_or(false);
+        if !is_file || !self.release_ids().is_empty() {
+            return Ok(());
+        }
+
+        let data = fs::read(&binary).map_err(|e| format!("Error reading existing binary: {e}"))?;
+        let release = Release {
+            id: 1,
+            commit: None,
+            uploader: "existing binary".to_owned(),
+            checksum: hex::encode(Sha256::digest(&data)),
+            time: Utc::now().timestamp(),
+            bundle: false,
+            key: None,
+        };
+        self.write_release(&release, |path| {
+            // Copied, the app keeps running from `run.command` until the switch
+            fs::copy(&binary, path.join(self.binary_name()))
+                .map(|_| ())
+                .map_err(|e| format!("Error copying existing binary: {e}"))
+        })?;
+
+        self.log(
+            LogLevel::Info,
+            format!("Kept the existing binary of `{}` as release 1", self.name),
+        );
+        Ok(())
+    }
+
+    /// Fills a staging folder with `fill` then moves it into `releases` in one rename,
+    /// so a failed deploy never leaves a half release
+    fn write_release(
+        &self,
+        release: &Release,
+        fill: impl FnOnce(&Path) -> Result<(), String>,
+    ) -> Result<(), String> {
+        // Next to the other releases so preserved links are the same depth
+        let temp = self
+            .project_path
+            .join("releases")
+            .join(format!(".{}.tmp", release.id));
+        let _ = fs::remove_dir_all(&temp);
+        let res = fs::create_dir_all(&temp)
+            .map_err(|e| format!("Error creating release: {e}"))
+            .and_then(|_| fill(&temp))
+            .and_then(|_| {
+                fs::write(temp.join(RELEASE_FILE), toml::to_string(release).unwrap())
+                    .and_then(|_| fs::rename(&temp, self.release_path(release.id)))
+                    .map_err(|e| format!("Error writing release: {e}"))
+            });
+
+        if let Err(e) = res {
+            let _ = fs::remove_dir_all(&temp);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Checks a detached signature against `releases.trusted_keys`, returning the key that made it.
+    /// Unsigned data is fine if no keys are trusted.
+    fn verify_signature(
+        &self,
+        data: &[u8],
+        signature: Option<&str>,
+    ) -> Result<Option<String>, String> {
+        let trusted = &self.config.releases.trusted_keys;
+        if trusted.is_empty() {
+            return Ok(None);
+        }
+
+        let signature = signature.ok_or("Release is not signed, a trusted signature is needed")?;
+        match signing::verify(trusted, data, signature) {
+            Some(i) => Ok(Some(i.to_owned())),
+            None => Err("Invalid signature, not signed by a trusted key".to_owned()),
+        }
+    }
+
+    /// Replaces preserved paths in a new release with links to `[project_path]/shared`.
+    /// The first bundle to have one seeds the shared copy.
+    fn link_preserved(&self, path: &Path) -> Result<(), String> {
+        for i in &self.config.releases.preserve {
+            let local = Path::new(i.trim_end_matches('/'));
+            let release = path.join(local);
+            let shared = self.project_path.join("shared").join(local);
+            let error = |e: io::Error| format!("Error preserving `{i}`: {e}");
+
+            if let Some(i) = shared.parent() {
+                fs::create_dir_all(i).map_err(error)?;
+            }
+
+            match release.symlink_metadata() {
+                Ok(_) if shared.symlink_metadata().is_err() => {
+                    fs::rename(&release, &shared).map_err(error)?
+                }
+                Ok(j) if j.is_dir() => fs::remove_dir_all(&release).map_err(error)?,
+                Ok(_) => fs::remove_file(&release).map_err(error)?,
+                // Trailing slash means a folder, anything else a file the app makes itself
+                Err(_) if i.ends_with('/') => fs::create_dir_all(&shared).map_err(error)?,
+                Err(_) => {}
+            }
+
+            // releases/N/[local] -> ../../shared/[local]
+            let depth = local.components().count() + 1;
+            let target = (0..depth)
+                .fold(PathBuf::new(), |x, _| x.join(".."))
+                .join("shared")
+                .join(local);
+            if let Some(i) = release.parent() {
+                fs::create_dir_all(i).map_err(error)?;
+            }
+            symlink(target, &release).map_err(error)?;
+        }
+
+        Ok(())
+    }
+
+    /// Points `current` at a release, and `run.command` at `current` for single binary releases
+    pub fn switch_release(&self, id: u32) -> Result<(), String> {
+        let release = self
+            .read_release(id)
+            .ok_or_else(|| format!("Release {id} does not exist"))?;
+
+        // Also covers releases from before keys were trusted, or from keys no longer trusted
+        let trusted = &self.config.releases.trusted_keys;
+        if !trusted.is_empty() && !release.key.map(|x| trusted.contains(&x)).unwrap_or(false) {
+            return Err(format!("Release {id} is not signed by a trusted key"));
+        }
+
+        swap_link(
+            &Path::new("releases").join(id.to_string()),
+            &self.project_path.join("current"),
+        )?;
+
+        // Bundles are run from inside `current`
+        if release.bundle {
+            return Ok(());
+        }
+
+        // Relative so the app folder can still be moved
+        let binary = self.project_path.join(&self.config.run.command);
+        let depth = Path::new(&self.config.run.command)
+            .components()
+            .filter(|x| matches!(x, Component::Normal(_)))
+            .count()
+            .saturating_sub(1);
+        let target = (0..depth)
+            .fold(PathBuf::new(), |x, _| x.join(".."))
+            .join("current")
+            .join(self.binary_name());
+
+        if fs::read_link(&binary).ok() != Some(target.to_owned()) {
+            if let Some(i) = binary.parent() {
+                fs::create_dir_all(i).map_err(|e| format!("Error creating binary folder: {e}"))?;
+            }
+            swap_link(&target, &binary)?;
+        }
+
+        Ok(())
+    }
+
+    /// Removes the oldest releases past `releases.keep`, never removing the current one
+    fn prune_releases(&self) {
+        let current = self.current_release();
+        let mut ids = self.release_ids();
+        ids.sort_unstable();
+
+        let remove = ids.len().saturating_sub(self.config.releases.keep);
+        for i in ids.into_iter().take(remove).filter(|x| Some(*x) != current) {
+            if let Err(e) = fs::remove_dir_all(self.release_path(i)) {
+                self.log(
+                    LogLevel::Error,
+                    format!("Error removing release {i} of `{}`: {e}", self.name),
+                );
+            }
+        }
+    }
+
+    fn release_ids(&self) -> Vec<u32> {
+        let dirs = match fs::read_dir(self.project_path.join("releases")) {
+            Ok(i) => i,
+            Err(_) => return Vec::new(),
+        };
+
+        dirs.filter_map(|x| x.ok())
+            .filter_map(|x| x.file_name().to_str()?.parse().ok())
+            .collect()
+    }
+
+    fn release_path(&self, id: u32) -> PathBuf {
+        self.project_path.join("releases").join(id.to_string())
+    }
+
+    /// File name of `run.command`, used for the binary in each release
+    fn binary_name(&self) -> String {
+        Path::new(&self.config.run.command)
+            .file_name()
+            .map(|x| x.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "binary".to_owned())
+    }
+}
+
+/// Replaces `link` with a symlink to `target` in one rename
+fn swap_link(target: &Path, link: &Path) -> Result<(), String> {
+    let temp = link.with_file_name(format!(
+        ".{}.new",
+        link.file_name().unwrap_or_default().to_string_lossy()
+    ));
+    let _ = fs::remove_file(&temp);
+
+    symlink(target, &temp)
+        .and_then(|_| fs::rename(&temp, link))
+        .map_err(|e| format!("Error linking `{}`: {e}", link.to_string_lossy()))
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn project(extra: &str) -> (TempDir, Project) {
+        let dir = TempDir::new().unwrap();
+        let project = Project::test(dir.path().to_owned(), extra);
+        (dir, project)
+    }
+
+    /// Contents of the binary at `run.command`
+    fn binary(project: &Project) -> String {
+        fs::read_to_string(project.project_path.join("app")).unwrap()
+    }
+
+    fn ids(project: &Project) -> Vec<u32> {
+        project.releases().into_iter().map(|x| x.id).collect()
+    }
+
+    #[test]
+    fn switches_current() {
+        let (_dir, project) = project("");
+        assert_eq!(project.add_release(b"1", None, None, "test").unwrap(), 1);
+        assert_eq!(project.add_release(b"2", None, None, "test").unwrap(), 2);
+
+        assert_eq!(project.current_release(), Some(2));
+        assert_eq!(
+            fs::read_link(project.project_path.join("current")).unwrap(),
+            Path::new("releases/2")
+        );
+        assert_eq!(binary(&project), "2");
+
+        project.switch_release(1).unwrap();
+        assert_eq!(project.current_release(), Some(1));
+        assert_eq!(binary(&project), "1");
+        assert!(project.switch_release(3).is_err());
+    }
+
+    #[test]
+    fn prunes_to_keep() {
+        let (_dir, project) = project("[releases]\nkeep = 2");
+        for i in ["1", "2", "3", "4"] {
+            project
+                .add_release(i.as_bytes(), None, None, "test")
+                .unwrap();
+        }
+
+        assert_eq!(ids(&project), [3, 4]);
+        assert_eq!(binary(&project), "4");
+    }
+
+    #[test]
+    fn rolls_back() {
+        let (_dir, project) = project("");
+        for i in ["1", "2", "3"] {
+            project
+                .add_release(i.as_bytes(), None, None, "test")
+                .unwrap();
+        }
+
+        assert_eq!(project.previous_release(), Some(2));
+        project.switch_release(2).unwrap();
+        assert_eq!(binary(&project), "2");
+
+        assert_eq!(project.previous_release(), Some(1));
+        project.switch_release(1).unwrap();
+        assert_eq!(binary(&project), "1");
+        assert_eq!(project.previous_release(), None);
+    }
+
+    #[test]
+    fn keeps_existing_binary() {
+        let (_dir, project) = project("");
+        fs::write(project.project_path.join("app"), "old").unwrap();
+
+        assert_eq!(project.add_release(b"new", None, None, "test").unwrap(), 2);
+        let releases = project.releases();
+        assert_eq!(releases[0].id, 1);
+        assert_eq!(releases[0].uploader, "existing binary");
+        assert_eq!(
+            fs::read_to_string(project.release_path(1).join("app")).unwrap(),
+            "old"
+        );
+        assert_eq!(binary(&project), "new");
+
+        project.switch_release(1).unwrap();
+        assert_eq!(binary(&project), "old");
+    }
+}
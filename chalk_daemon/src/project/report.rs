@@ -0,0 +1,219 @@
+use std::thread;
+
+use chalk_client::{
+    control::{self, Direction},
+    models::{AppEvent, AppReport, AppReportRequest},
+};
+use chrono::Utc;
+
+use super::{Process, Project};
+use crate::{LogLevel, LogSource};
+
+impl Project {
+    /// The daemon url the app sends its reports to
+    pub(super) fn report_url(&self) -> String {
+        let config = self.app.config.read();
+        let host = match config.api.host.as_str() {
+            "0.0.0.0" => "127.0.0.1".to_owned(),
+            "::" => "[::1]".to_owned(),
+            i if i.contains(':') && !i.starts_with('[') => format!("[{i}]"),
+            i => i.to_owned(),
+        };
+
+        format!("http://{host}:{}/app/report/{}", config.api.port, self.name)
+    }
+
+    /// Checks that a report was signed with the interface secret of the running app and is not a replay.
+    /// During a blue/green switch the new instance's secret is also accepted, returning true if it was the one used.
+    /// `header` looks up a request header by its name.
+    pub fn verify_report<'a>(
+        &self,
+        header: impl Fn(&str) -> Option<&'a str>,
+        body: &[u8],
+    ) -> Result<bool, String> {
+        if !self.status.read().is_running() {
+            return Err("App is not running".to_owned());
+        }
+
+        let (timestamp, nonce, signature) = match (
+            header(control::TIMESTAMP_HEADER),
+            header(control::NONCE_HEADER),
+            header(control::SIGNATURE_HEADER),
+        ) {
+            (Some(i), Some(j), Some(k)) => (i, j, k),
+            _ => return Err("Missing Signature".to_owned()),
+        };
+
+        let verify = |secret: &str| {
+            control::verify(secret, Direction::Report, timestamp, nonce, signature, body)
+        };
+        let (time, next) = match verify(&self.process.secret.read()) {
+            Ok(i) => (i, false),
+            Err(e) => match &*self.next.lock() {
+                Some(next) => verify(&next.secret.read()).map(|x| (x, true)),
+                None => Err(e),
+            }
+            .map_err(|e| e.to_string())?,
+        };
+
+        match self.report_nonces.insert(nonce, time) {
+            true => Ok(next),
+            false => Err("Request Replayed".to_owned()),
+        }
+    }
+
+    /// Applies a verified report from the app.
+    /// With `next` it goes to the new instance of a blue/green switch, see [`Project::verify_report`].
+    pub fn apply_report(&self, report: AppReportRequest, next: bool) {
+        let event = report.event;
+        match next {
+            true => match &*self.next.lock() {
+                Some(i) => self.merge_report(i, report),
+                None => return,
+            },
+            false => self.merge_report(&self.process, report),
+        }
+
+        match event {
+            Some(AppEvent::Reloading) => self
+                .app
+                .log(LogLevel::Info, format!("`{}` is reloading", self.name)),
+            Some(AppEvent::Restart) => {
+                self.log(
+                    LogLevel::Info,
+                    format!("`{}` asked to be restarted", self.name),
+                );
+                self.restart_later();
+            }
+            None => {}
+        }
+    }
+
+    /// Adds a report to what an instance reported before
+    fn merge_report(&self, process: &Process, report: AppReportRequest) {
+        let mut last = process.report.write();
+        let last = last.get_or_insert_with(AppReport::default);
+
+        if let Some(i) = report.ready {
+            if i && !last.ready {
+                self.log(LogLevel::Info, format!("`{}` reported ready", self.name));
+            }
+            last.ready = i;
+        }
+        if let Some(i) = report.status {
+            last.status = Some(i);
+        }
+        last.metrics.extend(report.metrics);
+        last.time = Utc::now().timestamp();
+    }
+
+    /// Restarts the app on its own thread, so whatever asked for it (EX: the app's report request) can finish first.
+    /// Dropped if the app is already being restarted or deployed.
+    pub(super) fn restart_later(&self) {
+        let (app, name) = (self.app.clone(), self.name.to_owned());
+        let lock = self.deploy_lock.clone();
+        thread::Builder::new()
+            .name("Restart".to_owned())
+            .spawn(move || {
+                let _restarting = match lock.try_lock() {
+                    Some(i) => i,
+                    None => {
+                        app.log_from(
+                            LogSource::App(name.to_owned()),
+                            LogLevel::Debug,
+                            format!("`{name}` is already restarting, ignoring the request"),
+                        );
+                        return;
+                    }
+                };
+
+                if let Some(i) = app.project(&name) {
+                    let _ = i.restart();
+                }
+            })
+            .unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chalk_client::control::SignedHeaders;
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::project::ProjectStatus;
+
+    const SECRET: &str = "chalk-test-secret";
+    const BODY: &[u8] = br#"{"ready":true}"#;
+
+    /// A running project with `SECRET` as its interface secret
+    fn project() -> (TempDir, Project) {
+        let dir = TempDir::new().unwrap();
+        let project = Project::test(dir.path().to_owned(), "");
+        *project.status.write() = ProjectStatus::Running;
+        *project.process.secret.write() = SECRET.to_owned();
+        (dir, project)
+    }
+
+    fn verify(project: &Project, signed: &SignedHeaders, timestamp: &str) -> Result<bool, String> {
+        let headers = [
+            (control::TIMESTAMP_HEADER, timestamp),
+            (control::NONCE_HEADER, signed.nonce.as_str()),
+            (control::SIGNATURE_HEADER, signed.signature.as_str()),
+        ];
+        project.verify_report(
+            |name| headers.iter().find(|x| x.0 == name).map(|x| x.1),
+            BODY,
+        )
+    }
+
+    #[test]
+    fn accepts_reports() {
+        let (_dir, project) = project();
+        let signed = control::sign(SECRET, Direction::Report, BODY);
+        assert_eq!(verify(&project, &signed, &signed.timestamp), Ok(false));
+    }
+
+    #[test]
+    fn accepts_reports_from_next_instance() {
+        let (_dir, project) = project();
+        let next = Process::new();
+        *next.secret.write() = "next-secret".to_owned();
+        *project.next.lock() = Some(next);
+
+        let signed = control::sign("next-secret", Direction::Report, BODY);
+        assert_eq!(verify(&project, &signed, &signed.timestamp), Ok(true));
+    }
+
+    #[test]
+    fn rejects_control_signatures() {
+        let (_dir, project) = project();
+        let signed = control::sign(SECRET, Direction::Control, BODY);
+        assert_eq!(
+            verify(&project, &signed, &signed.timestamp),
+            Err("Invalid Signature".to_owned())
+        );
+    }
+
+    #[test]
+    fn rejects_stale_reports() {
+        let (_dir, project) = project();
+        let signed = control::sign(SECRET, Direction::Report, BODY);
+        let stale = (control::now() - control::MAX_AGE - 1).to_string();
+        assert_eq!(
+            verify(&project, &signed, &stale),
+            Err("Request Expired".to_owned())
+        );
+    }
+
+    #[test]
+    fn rejects_replayed_reports() {
+        let (_dir, project) = project();
+        let signed = control::sign(SECRET, Direction::Report, BODY);
+        assert_eq!(verify(&project, &signed, &signed.timestamp), Ok(false));
+        assert_eq!(
+            verify(&project, &signed, &signed.timestamp),
+            Err("Request Replayed".to_owned())
+        );
+    }
+}
@@ -0,0 +1,64 @@
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use nix::sys::signal::Signal;
+use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM};
+use signal_hook::iterator::Signals;
+
+use crate::{App, Arc, LogLevel};
+
+pub fn init(app: Arc<App>) {
+    let mut signals = Signals::new([SIGINT, SIGTERM, SIGHUP]).unwrap();
+    thread::Builder::new()
+        .name("Signals".into())
+        .spawn(move || {
+            for i in signals.forever() {
+                match i {
+                    SIGHUP => reload(&app),
+                    _ => shutdown(&app),
+                }
+            }
+        })
+        .unwrap();
+}
+
+fn reload(app: &Arc<App>) {
+    app.log(LogLevel::Info, "Reloading config (SIGHUP)");
+    if let Err(e) = App::reload_config(app.clone()) {
+        app.log(LogLevel::Error, e);
+    }
+}
+
+fn shutdown(app: &Arc<App>) {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let old = app.last_exit_try.load(Ordering::Relaxed);
+    app.last_exit_try.store(now, Ordering::Relaxed);
+
+    if now - old > 5 {
+        app.log(LogLevel::Info, "Shutting down");
+        // `pre_stop` hooks can take a while, this thread has to be free for a second signal to force it
+        let projects = app.projects.read().clone();
+        thread::Builder::new()
+            .name("Shutdown".into())
+            .spawn(move || {
+                for i in projects {
+                    // The daemon is going down either way, so a failed `pre_stop` hook does not stop it
+                    if i.stop(Signal::SIGINT).is_err() {
+                        i.signal(Signal::SIGINT);
+                    }
+                }
+            })
+            .unwrap();
+        return;
+    }
+
+    app.log(LogLevel::Info, "Shutting down (FORCE)");
+    app.projects
+        .read()
+        .iter()
+        .for_each(|x| x.signal(Signal::SIGKILL));
+}
@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread;
+
+use inotify::{EventMask, Inotify, WatchDescriptor, WatchMask};
+use nix::sys::signal::Signal;
+
+use chalk_client::models::ProblemLevel;
+
+use crate::{
+    check,
+    config::ConfigPolicy,
+    project::{ProjectConfig, STOP_TIMEOUT},
+    App, LogLevel, Project,
+};
+
+/// Events on the apps folder
+const APPS_MASK: WatchMask = WatchMask::CREATE
+    .union(WatchMask::DELETE)
+    .union(WatchMask::MOVED_TO)
+    .union(WatchMask::MOVED_FROM);
+
+/// Events on a project folder
+const PROJECT_MASK: WatchMask = WatchMask::CLOSE_WRITE.union(WatchMask::MOVED_TO);
+
+struct Watcher {
+    app: Arc<App>,
+    inotify: Inotify,
+
+    /// Path of the apps folder
+    apps_path: PathBuf,
+    /// Watch on the apps folder
+    apps_watch: WatchDescriptor,
+    /// Watches on project folders
+    projects: HashMap<WatchDescriptor, PathBuf>,
+}
+
+pub fn start(app: Arc<App>) {
+    if !app.config.read().watch.enabled {
+        return;
+    }
+
+    thread::Builder::new()
+        .name("Watcher".into())
+        .spawn(|| Watcher::new(app).run())
+        .unwrap();
+}
+
+impl Watcher {
+    fn new(app: Arc<App>) -> Self {
+        let inotify = Inotify::init().expect("Error starting inotify");
+        let apps_path = app.apps_path();
+        let apps_watch = inotify
+            .watches()
+            .add(&apps_path, APPS_MASK)
+            .expect("Error watching apps folder");
+
+        let mut this = Self {
+            app,
+            inotify,
+            apps_path,
+            apps_watch,
+            projects: HashMap::new(),
+        };
+
+        for i in fs::read_dir(&this.apps_path)
+            .unwrap()
+            .map(|x| x.unwrap().path())
+            .filter(|x| x.is_dir())
+        {
+            this.watch_project(i);
+        }
+
+        this
+    }
+
+    fn run(mut self) {
+        let mut buffer = [0; 4096];
+        loop {
+            let events = self
+                .inotify
+                .read_events_blocking(&mut buffer)
+                .expect("Error reading inotify events")
+                .filter_map(|x| Some((x.wd, x.mask, x.name?.to_owned())))
+                .collect::<Vec<_>>();
+
+            for (wd, mask, name) in events {
+                self.handle(wd, mask, name);
+            }
+        }
+    }
+
+    fn handle(&mut self, wd: WatchDescriptor, mask: EventMask, name: OsString) {
+        // Project folder added or removed
+        if wd == self.apps_watch {
+            if !mask.contains(EventMask::ISDIR) {
+                return;
+            }
+
+            let path = self.apps_path.join(name);
+            if mask.intersects(EventMask::CREATE | EventMask::MOVED_TO) {
+                let has_config = path.join("config.toml").exists();
+                self.watch_project(path);
+                if has_config {
+                    Project::rescan(self.app.clone());
+                }
+                return;
+            }
+
+            self.unwatch_project(&path);
+            Project::unload_missing(self.app.clone());
+            return;
+        }
+
+        // Project config changed
+        if name != "config.toml" {
+            return;
+        }
+
+        if let Some(path) = self.projects.get(&wd) {
+            config_changed(&self.app, path);
+        }
+    }
+
+    fn watch_project(&mut self, path: PathBuf) {
+        match self.inotify.watches().add(&path, PROJECT_MASK) {
+            Ok(i) => {
+                self.projects.insert(i, path);
+            }
+            Err(e) => self.app.log(
+                LogLevel::Error,
+                format!("Error watching `{}`: {e}", path.to_string_lossy()),
+            ),
+        }
+    }
+
+    fn unwatch_project(&mut self, path: &Path) {
+        let wd = match self.projects.iter().find(|x| x.1 == path) {
+            Some(i) => i.0.to_owned(),
+            None => return,
+        };
+
+        // Fails if the folder was deleted, as the watch is already gone
+        let _ = self.inotify.watches().remove(wd.clone());
+        self.projects.remove(&wd);
+    }
+}
+
+fn config_changed(app: &Arc<App>, path: &Path) {
+    // Cloned out so the list is not locked while the app is stopped
+    let project = app
+        .projects
+        .read()
+        .iter()
+        .find(|x| x.project_path == path)
+        .cloned();
+    let project = match project {
+        Some(i) => i,
+        None => {
+            // A folder without a (valid) config got one
+            Project::rescan(app.clone());
+            return;
+        }
+    };
+
+    let raw_config = match fs::read_to_string(path.join("config.toml")) {
+        Ok(i) => i,
+        Err(_) => return,
+    };
+
+    let config = match toml::from_str::<ProjectConfig>(&raw_config) {
+        Ok(i) => i,
+        Err(e) => {
+            project.log(
+                LogLevel::Warn,
+                format!("Ignoring invalid config change for `{}`: {e}", project.name),
+            );
+            return;
+        }
+    };
+
+    if config == project.config || project.staged_config.lock().as_ref() == Some(&config) {
+        return;
+    }
+
+    // Same checks as a reload from the api
+    if let Some(e) = check::check_project(app, &raw_config, Some(path))
+        .into_iter()
+        .find(|x| x.level == ProblemLevel::Error)
+    {
+        project.log(
+            LogLevel::Warn,
+            format!("Ignoring invalid config change for `{}`: {e}", project.name),
+        );
+        return;
+    }
+
+    *project.staged_config.lock() = Some(config);
+    if !project.status.read().is_running() {
+        Project::apply_staged(app.clone());
+        return;
+    }
+
+    let policy = app.config.read().watch.config_policy;
+    match policy {
+        ConfigPolicy::Restart => project.log(
+            LogLevel::Info,
+            format!(
+                "Staged new config for `{}`. It will be applied when the app restarts.",
+                project.name
+            ),
+        ),
+        ConfigPolicy::Immediate => {
+            let _deploying = project.deploy_lock.lock();
+            // A deploy that finished in the meantime already restarted it with the new config
+            if project.staged_config.lock().is_none() {
+                return;
+            }
+
+            project.log(
+                LogLevel::Info,
+                format!("Restarting `{}` to apply its new config", project.name),
+            );
+            if let Err(e) = project.stop_wait(Signal::SIGINT, STOP_TIMEOUT) {
+                project.log(
+                    LogLevel::Error,
+                    format!("Not restarting `{}`: {e}", project.name),
+                );
+                return;
+            }
+
+            // Errors are logged by `start`
+            Project::apply_staged(app.clone());
+            let new = app
+                .projects
+                .read()
+                .iter()
+                .find(|x| x.project_path == path)
+                .cloned();
+            if let Some(i) = new {
+                let _ = i.start();
+            }
+        }
+    }
+}